@@ -0,0 +1,190 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Exposes each virtual authenticator tracked by `TestTokenManager` as a D-Bus object, gated
+//! behind the `dbus` feature. This lets a separate process own a virtual token and answer
+//! register/sign ceremonies interactively (useful for CI harnesses and remote-controlled browser
+//! automation where the authenticator shouldn't have to live in the browser process), while
+//! sharing the same `Mutex<HashMap<u64, TestToken>>` that the in-process `register`/`sign` callers
+//! already use.
+//!
+//! Pulled in from the crate root as `#[cfg(feature = "dbus")] mod dbus_bridge;`, with the `dbus`
+//! feature gating the optional `zbus` dependency in `Cargo.toml`.
+
+use std::sync::mpsc;
+use std::sync::Arc;
+
+use authenticator::authenticatorservice::{RegisterArgs, SignArgs};
+use authenticator::statecallback::StateCallback;
+use authenticator::StatusUpdate;
+use zbus::{dbus_interface, ConnectionBuilder, SignalContext};
+
+use crate::test_token::TestTokenManager;
+
+const BUS_NAME: &str = "org.mozilla.webauthn.VirtualAuthenticator";
+const OBJECT_PATH_PREFIX: &str = "/org/mozilla/webauthn/VirtualAuthenticator";
+
+/// The D-Bus-facing object for one `authenticator_id` already present in `manager`'s state.
+/// Creating one of these does not create the underlying token; that still happens via
+/// `TestTokenManager::add_virtual_authenticator`.
+pub struct VirtualAuthenticatorDbus {
+    manager: Arc<TestTokenManager>,
+    authenticator_id: u64,
+}
+
+#[dbus_interface(name = "org.mozilla.webauthn.VirtualAuthenticator1")]
+impl VirtualAuthenticatorDbus {
+    // TODO: thread the real `RegisterArgs`/`SignArgs` fields (rp, user, exclude/allow lists,
+    // extensions) through as D-Bus method arguments instead of the origin/rp_id/user_id we accept
+    // today; for now this only covers the common case exercised by conformance suites.
+    async fn register(
+        &self,
+        origin: String,
+        rp_id: String,
+        user_id: Vec<u8>,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let ctap_args = RegisterArgs {
+            origin,
+            relying_party: authenticator::ctap2::server::RelyingParty {
+                id: rp_id,
+                name: None,
+                icon: None,
+            },
+            user: authenticator::ctap2::server::User {
+                id: user_id,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let (status_tx, status_rx) = mpsc::channel();
+        let (result_tx, result_rx) = mpsc::sync_channel(1);
+        let callback = StateCallback::new(Box::new(move |result| {
+            let _ = result_tx.send(result);
+        }));
+
+        self.manager.register(0, ctap_args, status_tx, callback);
+        self.forward_status_updates(status_rx, &ctxt).await;
+
+        result_rx
+            .recv()
+            .map_err(|_| zbus::fdo::Error::Failed("no virtual authenticator answered".into()))?
+            .map(|result| result.attestation_object)
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
+    async fn sign(
+        &self,
+        origin: String,
+        rp_id: String,
+        allow_list: Vec<Vec<u8>>,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let ctap_args = SignArgs {
+            origin,
+            relying_party_id: rp_id,
+            allow_list: allow_list
+                .into_iter()
+                .map(|id| authenticator::ctap2::server::PublicKeyCredentialDescriptor {
+                    id,
+                    transports: vec![],
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let (status_tx, status_rx) = mpsc::channel();
+        let (result_tx, result_rx) = mpsc::sync_channel(1);
+        let callback = StateCallback::new(Box::new(move |result| {
+            let _ = result_tx.send(result);
+        }));
+
+        self.manager.sign(0, ctap_args, status_tx, callback);
+        self.forward_status_updates(status_rx, &ctxt).await;
+
+        result_rx
+            .recv()
+            .map_err(|_| zbus::fdo::Error::Failed("no virtual authenticator answered".into()))?
+            .map(|result| result.signature)
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
+    async fn add_credential(
+        &self,
+        credential_id: Vec<u8>,
+        private_key: Vec<u8>,
+        user_handle: Vec<u8>,
+        sign_count: u32,
+        rp_id: String,
+        is_resident_credential: bool,
+    ) -> zbus::fdo::Result<()> {
+        self.manager
+            .add_credential(
+                self.authenticator_id,
+                &credential_id,
+                &private_key,
+                if user_handle.is_empty() {
+                    None
+                } else {
+                    Some(user_handle.as_slice())
+                },
+                sign_count,
+                rp_id,
+                is_resident_credential,
+            )
+            .map_err(|_| zbus::fdo::Error::Failed("unknown virtual authenticator".into()))
+    }
+
+    async fn set_user_verified(&self, is_user_verified: bool) -> zbus::fdo::Result<()> {
+        self.manager
+            .set_user_verified(self.authenticator_id, is_user_verified)
+            .map_err(|_| zbus::fdo::Error::Failed("unknown virtual authenticator".into()))
+    }
+
+    async fn delete_all_credentials(&self) -> zbus::fdo::Result<()> {
+        self.manager
+            .remove_all_credentials(self.authenticator_id)
+            .map_err(|_| zbus::fdo::Error::Failed("unknown virtual authenticator".into()))
+    }
+
+    #[dbus_interface(signal)]
+    async fn status_update(ctxt: &SignalContext<'_>, message: String) -> zbus::Result<()>;
+}
+
+impl VirtualAuthenticatorDbus {
+    // `register`/`sign` report progress (e.g. "select a device", PIN prompts) on a
+    // `Sender<StatusUpdate>` before the final result arrives; relay each one as a signal so a
+    // remote owner can react the way an in-process UI would.
+    async fn forward_status_updates(
+        &self,
+        status_rx: mpsc::Receiver<StatusUpdate>,
+        ctxt: &SignalContext<'_>,
+    ) {
+        while let Ok(update) = status_rx.try_recv() {
+            let _ = Self::status_update(ctxt, format!("{update:?}")).await;
+        }
+    }
+}
+
+/// Publishes `authenticator_id` (already created via
+/// `TestTokenManager::add_virtual_authenticator`) on the session bus at
+/// `{OBJECT_PATH_PREFIX}/{authenticator_id}`, returning the connection that owns the object.
+pub async fn publish(
+    manager: Arc<TestTokenManager>,
+    authenticator_id: u64,
+) -> zbus::Result<zbus::Connection> {
+    let object_path = format!("{OBJECT_PATH_PREFIX}/{authenticator_id}");
+    ConnectionBuilder::session()?
+        .name(BUS_NAME)?
+        .serve_at(
+            object_path,
+            VirtualAuthenticatorDbus {
+                manager,
+                authenticator_id,
+            },
+        )?
+        .build()
+        .await
+}
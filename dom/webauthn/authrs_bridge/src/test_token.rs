@@ -3,15 +3,25 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use authenticator::authenticatorservice::{RegisterArgs, SignArgs};
-use authenticator::crypto::{ecdsa_p256_sha256_sign_raw, COSEAlgorithm, COSEKey, SharedSecret};
+use authenticator::crypto::{
+    ecdsa_p256_pubkey_from_private_raw, ecdsa_p256_sha256_sign_raw,
+    eddsa_ed25519_pubkey_from_private_raw, eddsa_ed25519_sign_raw, rs256_pubkey_from_private_raw,
+    rs256_sha256_sign_raw, COSEAlgorithm, COSEKey, SharedSecret,
+};
 use authenticator::ctap2::{
     attestation::{
         AAGuid, AttestationObject, AttestationStatement, AttestationStatementPacked,
-        AttestedCredentialData, AuthenticatorData, AuthenticatorDataFlags, Extension,
+        AttestationType, AttestedCredentialData, AuthenticatorData, AuthenticatorDataFlags,
+        CredentialProtectionPolicy, Extension, HmacSecretInput, HmacSecretResponse,
     },
     client_data::ClientDataHash,
     commands::{
+        authenticator_config::{AuthConfigSubCommand, AuthenticatorConfig},
+        bio_enrollment::{BioEnrollment, BioEnrollmentResponse, BioEnrollmentSubCommand},
         client_pin::{ClientPIN, ClientPinResponse, PINSubcommand},
+        credential_management::{
+            CredentialManagement, CredentialManagementResponse, CredentialManagementSubCommand,
+        },
         get_assertion::{Assertion, GetAssertion, GetAssertionResponse, GetAssertionResult},
         get_info::{AuthenticatorInfo, AuthenticatorOptions, AuthenticatorVersion},
         get_version::{GetVersion, U2FInfo},
@@ -30,8 +40,8 @@ use authenticator::{RegisterResult, SignResult, StatusUpdate};
 use nserror::{nsresult, NS_ERROR_FAILURE, NS_ERROR_INVALID_ARG, NS_ERROR_NOT_IMPLEMENTED, NS_OK};
 use nsstring::{nsACString, nsCString};
 use rand::{thread_rng, RngCore};
-use std::cell::{Ref, RefCell};
-use std::collections::{hash_map::Entry, HashMap};
+use std::cell::{Cell, Ref, RefCell};
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
 use std::ops::{Deref, DerefMut};
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::mpsc::Sender;
@@ -45,14 +55,51 @@ const VIRTUAL_TOKEN_AAGUID: AAGuid = AAGuid([
     0x68, 0xe1, 0x00, 0xa5, 0x0b, 0x47, 0x91, 0x04, 0xb8, 0x54, 0x97, 0xa9, 0xba, 0x51, 0x06, 0x38,
 ]);
 
+// Dispatches a raw signature over `data` to the signer matching `alg`, used both for assertion
+// signatures and the packed attestation statement's signature.
+fn sign_with(alg: COSEAlgorithm, privkey: &[u8], data: &[u8]) -> Vec<u8> {
+    match alg {
+        COSEAlgorithm::ES256 => ecdsa_p256_sha256_sign_raw(privkey, data).unwrap(),
+        COSEAlgorithm::EDDSA => eddsa_ed25519_sign_raw(privkey, data).unwrap(),
+        COSEAlgorithm::RS256 => rs256_sha256_sign_raw(privkey, data).unwrap(),
+        _ => unreachable!("TestToken never stores a credential with an unsupported algorithm"),
+    }
+}
+
+// Recomputes the COSE public key from a stored private key. We don't cache the public key
+// alongside it: credentials we generate ourselves in `make_credentials` and ones injected via
+// the WebDriver virtual authenticator API (which only ever hands us a raw private key) both go
+// through this, so there's only ever one source of truth for what a credential's public key is.
+fn derive_public_key(alg: COSEAlgorithm, privkey: &[u8]) -> COSEKey {
+    match alg {
+        COSEAlgorithm::ES256 => ecdsa_p256_pubkey_from_private_raw(privkey).unwrap(),
+        COSEAlgorithm::EDDSA => eddsa_ed25519_pubkey_from_private_raw(privkey).unwrap(),
+        COSEAlgorithm::RS256 => rs256_pubkey_from_private_raw(privkey).unwrap(),
+        _ => unreachable!("TestToken never stores a credential with an unsupported algorithm"),
+    }
+}
+
+// CTAP2.1 11.2.9.1.1: the authenticator keeps two independent CredRandom secrets per credential,
+// one used when the assertion that follows performed UV and one used when it didn't, so a
+// platform can't correlate a credential across UV states via the hmac-secret output.
+#[derive(Debug, Clone, Copy)]
+struct HmacSecretCredRandom {
+    with_uv: [u8; 32],
+    without_uv: [u8; 32],
+}
+
 #[derive(Debug)]
 struct TestTokenCredential {
     id: Vec<u8>,
     privkey: Vec<u8>,
+    alg: COSEAlgorithm,
     user_handle: Vec<u8>,
     sign_count: AtomicU32,
     is_discoverable_credential: bool,
     rp: RelyingPartyWrapper,
+    // Set only if the platform requested the hmac-secret extension at registration time.
+    hmac_secret_cred_random: Option<HmacSecretCredRandom>,
+    cred_protect: Option<CredentialProtectionPolicy>,
 }
 
 impl TestTokenCredential {
@@ -60,6 +107,8 @@ impl TestTokenCredential {
         &self,
         client_data_hash: &ClientDataHash,
         flags: AuthenticatorDataFlags,
+        number_of_credentials: Option<u64>,
+        extensions: Extension,
     ) -> GetAssertionResponse {
         let credentials = Some(PublicKeyCredentialDescriptor {
             id: self.id.clone(),
@@ -71,7 +120,7 @@ impl TestTokenCredential {
             flags,
             counter: self.sign_count.fetch_add(1, Ordering::Relaxed),
             credential_data: None,
-            extensions: Extension::default(),
+            extensions,
         };
 
         let user = Some(User {
@@ -81,17 +130,25 @@ impl TestTokenCredential {
 
         let mut data = auth_data.to_vec().unwrap();
         data.extend_from_slice(client_data_hash.as_ref());
-        let signature = ecdsa_p256_sha256_sign_raw(&self.privkey, &data).unwrap();
+        let signature = sign_with(self.alg, &self.privkey, &data);
         GetAssertionResponse {
             credentials,
             auth_data,
             signature,
             user,
-            number_of_credentials: Some(1),
+            number_of_credentials,
         }
     }
 }
 
+// The batch attestation key material used when `attestation_type` is `AttestationType::Basic`
+// (or `AttCA`, which we treat identically since nothing about credential generation differs).
+#[derive(Debug, Clone)]
+struct BatchAttestation {
+    certificate: Vec<u8>,
+    private_key: Vec<u8>,
+}
+
 #[derive(Debug)]
 struct TestToken {
     protocol: FidoProtocol,
@@ -100,13 +157,75 @@ struct TestToken {
     has_user_verification: bool,
     is_user_consenting: bool,
     is_user_verified: bool,
+    // Ordered by preference; `make_credentials` selects the first entry here that also appears
+    // in the request's `pub_cred_params`.
+    supported_algorithms: Vec<COSEAlgorithm>,
+    aaguid: AAGuid,
+    attestation_type: Cell<AttestationType>,
+    // Only consulted when `attestation_type` is `Basic`/`AttCA`.
+    batch_attestation: Option<BatchAttestation>,
     // This is modified in `make_credentials` which takes a &TestToken, but we only allow one transaction at a time.
     credentials: RefCell<Vec<TestTokenCredential>>,
-    pin_token: [u8; 32],
+    pin_token: Cell<[u8; 32]>,
     shared_secret: Option<SharedSecret>,
     authenticator_info: Option<AuthenticatorInfo>,
+    // The left16(SHA-256(pin)) of the currently configured PIN, if any has been set via
+    // `SetPin`/`ChangePin`.
+    pin_hash: RefCell<Option<[u8; 16]>>,
+    pin_retries: Cell<u8>,
+    // Cursors left behind by `enumerateRPsBegin`/`enumerateCredentialsBegin`, consumed one entry
+    // at a time by the matching `GetNextRP`/`GetNextCredential` subcommand.
+    rp_enumeration: RefCell<Option<RpEnumerationCursor>>,
+    cred_enumeration: RefCell<Option<CredEnumerationCursor>>,
+    // Assertions left over from a `get_assertion` call against a discoverable credential, beyond
+    // the first one returned, consumed one at a time by `authenticatorGetNextAssertion`.
+    assertion_queue: RefCell<VecDeque<Assertion>>,
+    // authenticatorConfig state.
+    always_uv: Cell<bool>,
+    min_pin_length: Cell<u8>,
+    enterprise_attestation_enabled: Cell<bool>,
+    // authenticatorBioEnrollment state.
+    bio_templates: RefCell<Vec<BioTemplate>>,
+    // Test-only: whether the next simulated biometric sample (an enrollment capture or an
+    // internal-UV ceremony) matches. Set via `TestTokenManager::set_next_uv_sample_matches`.
+    next_uv_sample_matches: Cell<bool>,
+    uv_retries: Cell<u8>,
+}
+
+#[derive(Debug)]
+struct RpEnumerationCursor {
+    rps: Vec<RelyingPartyWrapper>,
+    next: usize,
+}
+
+#[derive(Debug)]
+struct CredEnumerationCursor {
+    rp_id_hash: Vec<u8>,
+    credential_ids: Vec<Vec<u8>>,
+    next: usize,
+}
+
+#[derive(Debug)]
+struct BioTemplate {
+    template_id: Vec<u8>,
+    friendly_name: Option<String>,
 }
 
+// CTAP2.1 6.5.5.4: an authenticator that supports clientPIN starts with 8 PIN retries and
+// becomes unusable (`PinBlocked`) once they are exhausted.
+const MAX_PIN_RETRIES: u8 = 8;
+
+// CTAP2.1 6.3: three consecutive failed internal UV samples temporarily block UV (`UvBlocked`)
+// and force a PIN/UV Auth Token fallback.
+const MAX_UV_RETRIES: u8 = 3;
+
+// Arbitrary resident-credential capacity reported via `getCredsMetadata`; we don't actually
+// enforce a hard cap elsewhere.
+const MAX_RESIDENT_CREDENTIALS: usize = 100;
+
+// CTAP2.1 6.11: the default minimum PIN length before `setMinPINLength` raises it.
+const DEFAULT_MIN_PIN_LENGTH: u8 = 4;
+
 impl TestToken {
     fn new(
         versions: Vec<AuthenticatorVersion>,
@@ -114,9 +233,14 @@ impl TestToken {
         has_user_verification: bool,
         is_user_consenting: bool,
         is_user_verified: bool,
+        supported_algorithms: Vec<COSEAlgorithm>,
+        aaguid: AAGuid,
+        attestation_type: AttestationType,
+        batch_attestation: Option<BatchAttestation>,
     ) -> TestToken {
         let mut pin_token = [0u8; 32];
         thread_rng().fill_bytes(&mut pin_token);
+        let pin_token = Cell::new(pin_token);
         Self {
             protocol: FidoProtocol::CTAP2,
             versions,
@@ -124,29 +248,222 @@ impl TestToken {
             has_user_verification,
             is_user_consenting,
             is_user_verified,
+            supported_algorithms,
+            aaguid,
+            attestation_type: Cell::new(attestation_type),
+            batch_attestation,
             credentials: RefCell::new(vec![]),
             pin_token,
             shared_secret: None,
             authenticator_info: None,
+            pin_hash: RefCell::new(None),
+            pin_retries: Cell::new(MAX_PIN_RETRIES),
+            rp_enumeration: RefCell::new(None),
+            cred_enumeration: RefCell::new(None),
+            assertion_queue: RefCell::new(VecDeque::new()),
+            always_uv: Cell::new(false),
+            min_pin_length: Cell::new(DEFAULT_MIN_PIN_LENGTH),
+            enterprise_attestation_enabled: Cell::new(false),
+            bio_templates: RefCell::new(vec![]),
+            next_uv_sample_matches: Cell::new(true),
+            uv_retries: Cell::new(MAX_UV_RETRIES),
+        }
+    }
+
+    // The platform pads the UTF-8 PIN with trailing 0x00 bytes out to a 16-byte boundary before
+    // encrypting it; undo that here to recover the PIN bytes.
+    fn unpad_pin(padded: &[u8]) -> &[u8] {
+        let end = padded.iter().position(|&b| b == 0).unwrap_or(padded.len());
+        &padded[..end]
+    }
+
+    fn pin_hash_of(pin: &[u8]) -> [u8; 16] {
+        let digest = authenticator::crypto::sha256(pin);
+        let mut truncated = [0u8; 16];
+        truncated.copy_from_slice(&digest[..16]);
+        truncated
+    }
+
+    // Validates `pin_uv_auth_param` against `self.pin_token`, independent of whether PIN/UV auth
+    // protocol One or Two was negotiated: protocol One truncates the HMAC to 16 bytes, protocol
+    // Two uses the full 32 bytes, and the two are distinguishable by the length the platform sent.
+    fn verify_pin_uv_auth_param(&self, message: &[u8], pin_uv_auth_param: &[u8]) -> bool {
+        let mac = authenticator::crypto::hmac_sha256(&self.pin_token.get(), message);
+        match pin_uv_auth_param.len() {
+            16 => mac[..16] == pin_uv_auth_param[..],
+            32 => mac[..] == pin_uv_auth_param[..],
+            _ => false,
+        }
+    }
+
+    // Consults the simulated biometric sample set via `set_next_uv_sample_matches` to decide
+    // whether an internal-UV ceremony (a fingerprint scan standing in for a PIN) succeeds,
+    // maintaining a CTAP2.1 6.3-style retry counter that blocks UV after repeated mismatches.
+    fn perform_internal_uv(&self) -> Result<(), HIDError> {
+        if self.uv_retries.get() == 0 {
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::UvBlocked,
+                None,
+            )));
+        }
+        if self.next_uv_sample_matches.get() {
+            self.uv_retries.set(MAX_UV_RETRIES);
+            Ok(())
+        } else {
+            self.uv_retries.set(self.uv_retries.get() - 1);
+            Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::UvInvalid,
+                None,
+            )))
+        }
+    }
+
+    // Decrypts `pinHashEnc` and compares it against the stored PIN hash, maintaining the retry
+    // counter per CTAP2.1 6.5.5.4.
+    fn check_pin_hash_enc(
+        &self,
+        secret: &SharedSecret,
+        pin_hash_enc: &[u8],
+    ) -> Result<(), HIDError> {
+        if self.pin_retries.get() == 0 {
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::PinBlocked,
+                None,
+            )));
+        }
+
+        let stored_hash = self
+            .pin_hash
+            .borrow()
+            .ok_or(HIDError::Command(CommandError::StatusCode(
+                StatusCode::PinNotSet,
+                None,
+            )))?;
+
+        let decrypted = secret.decrypt(pin_hash_enc).map_err(|_| HIDError::DeviceError)?;
+        if decrypted.len() != 16 || decrypted[..] != stored_hash[..] {
+            self.pin_retries.set(self.pin_retries.get() - 1);
+            if self.pin_retries.get() == 0 {
+                return Err(HIDError::Command(CommandError::StatusCode(
+                    StatusCode::PinBlocked,
+                    None,
+                )));
+            }
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::PinInvalid,
+                None,
+            )));
+        }
+
+        self.pin_retries.set(MAX_PIN_RETRIES);
+        Ok(())
+    }
+
+    // CTAP2.1 12.4: a credProtect'd credential may only be disclosed if UV was performed for
+    // this assertion, or (for userVerificationOptionalWithCredentialIDList) its ID was already
+    // named explicitly in the request's allow_list.
+    fn credential_disclosure_allowed(
+        credential: &TestTokenCredential,
+        uv_performed: bool,
+        explicit_allow_list_match: bool,
+    ) -> bool {
+        match credential.cred_protect {
+            Some(CredentialProtectionPolicy::UserVerificationRequired) => uv_performed,
+            Some(CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList) => {
+                uv_performed || explicit_allow_list_match
+            }
+            Some(CredentialProtectionPolicy::UserVerificationOptional) | None => true,
+        }
+    }
+
+    // Builds the response `extensions` map for one assertion, answering hmac-secret if the
+    // platform requested it and the credential has a CredRandom to answer it with. `flags` is the
+    // assertion's authenticator data flags, used to pick the UV or non-UV CredRandom.
+    fn build_assertion_extensions(
+        &self,
+        credential: &TestTokenCredential,
+        flags: AuthenticatorDataFlags,
+        req: &GetAssertion,
+    ) -> Result<Extension, HIDError> {
+        let hmac_secret = match (&req.extensions.hmac_secret, credential.hmac_secret_cred_random) {
+            (Some(input), Some(cred_random)) => {
+                let cred_random = if flags.contains(AuthenticatorDataFlags::USER_VERIFIED) {
+                    cred_random.with_uv
+                } else {
+                    cred_random.without_uv
+                };
+                Some(self.compute_hmac_secret_response(cred_random, input)?)
+            }
+            _ => None,
+        };
+        Ok(Extension {
+            hmac_secret,
+            ..Default::default()
+        })
+    }
+
+    // CTAP2.1 11.2.9.2.1: HMAC-SHA-256(CredRandom, salt) for each requested 32-byte salt,
+    // concatenated and encrypted back to the platform with the PIN/UV shared secret.
+    fn compute_hmac_secret_response(
+        &self,
+        cred_random: [u8; 32],
+        input: &HmacSecretInput,
+    ) -> Result<HmacSecretResponse, HIDError> {
+        let secret = self.shared_secret.as_ref().ok_or(HIDError::DeviceError)?;
+
+        let mac = secret.authenticate(&input.salt_enc);
+        let auth_len = input.salt_auth.len();
+        if mac.len() < auth_len || mac[..auth_len] != input.salt_auth[..] {
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::InvalidParameter,
+                None,
+            )));
+        }
+
+        let salts = secret.decrypt(&input.salt_enc).map_err(|_| HIDError::DeviceError)?;
+        // CTAP2.1 11.2.9.1.2: saltEnc decrypts to either one 32-byte salt or two concatenated
+        // 32-byte salts; anything else is malformed (or a deliberately short salt_enc that
+        // happened to produce a valid MAC over fewer AES blocks).
+        if salts.len() != 32 && salts.len() != 64 {
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::InvalidLength,
+                None,
+            )));
         }
+        let mut output = authenticator::crypto::hmac_sha256(&cred_random, &salts[..32]).to_vec();
+        if salts.len() >= 64 {
+            output.extend_from_slice(&authenticator::crypto::hmac_sha256(
+                &cred_random,
+                &salts[32..64],
+            ));
+        }
+
+        let encrypted = secret.encrypt(&output).map_err(|_| HIDError::DeviceError)?;
+        Ok(HmacSecretResponse::Secret(encrypted))
     }
 
     fn insert_credential(
         &self,
         id: &[u8],
         privkey: &[u8],
+        alg: COSEAlgorithm,
         rp_id: &RelyingPartyWrapper,
         is_discoverable_credential: bool,
         user_handle: &[u8],
         sign_count: u32,
+        hmac_secret_cred_random: Option<HmacSecretCredRandom>,
+        cred_protect: Option<CredentialProtectionPolicy>,
     ) {
         let c = TestTokenCredential {
             id: id.to_vec(),
             privkey: privkey.to_vec(),
+            alg,
             rp: rp_id.clone(),
             is_discoverable_credential,
             user_handle: user_handle.to_vec(),
             sign_count: AtomicU32::new(sign_count),
+            hmac_secret_cred_random,
+            cred_protect,
         };
 
         let mut credlist = self.credentials.borrow_mut();
@@ -181,6 +498,27 @@ impl TestToken {
             .binary_search_by_key(&id, |probe| &probe.id)
             .is_ok()
     }
+
+    // Shared by `EnumerateCredentialsBegin` and `EnumerateCredentialsGetNextCredential`, which
+    // return the same per-credential fields and only differ in whether `totalCredentials` is set.
+    fn credential_entry_response(
+        credential: &TestTokenCredential,
+        total_credentials: Option<u64>,
+    ) -> CredentialManagementResponse {
+        CredentialManagementResponse {
+            user: Some(User {
+                id: credential.user_handle.clone(),
+                ..Default::default()
+            }),
+            credential_id: Some(PublicKeyCredentialDescriptor {
+                id: credential.id.clone(),
+                transports: vec![],
+            }),
+            public_key: Some(derive_public_key(credential.alg, &credential.privkey)),
+            total_credentials,
+            ..Default::default()
+        }
+    }
 }
 
 impl FidoDevice for TestToken {
@@ -259,6 +597,41 @@ impl FidoDeviceIO for TestToken {
 }
 
 impl VirtualFidoDevice for TestToken {
+    fn authenticator_config(&self, req: &AuthenticatorConfig) -> Result<(), HIDError> {
+        // TODO: authenticate pinUvAuthParam over the exact CBOR-encoded subCommandParams; for now
+        // we only require that one was supplied, as `credential_management` and `bio_enrollment`
+        // also do.
+        if req.pin_uv_auth_param.is_none() {
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::PinRequired,
+                None,
+            )));
+        }
+
+        match &req.subcommand {
+            AuthConfigSubCommand::EnableEnterpriseAttestation => {
+                self.enterprise_attestation_enabled.set(true);
+                Ok(())
+            }
+            AuthConfigSubCommand::ToggleAlwaysUv => {
+                self.always_uv.set(!self.always_uv.get());
+                Ok(())
+            }
+            AuthConfigSubCommand::SetMinPinLength {
+                new_min_pin_length, ..
+            } => {
+                if *new_min_pin_length < self.min_pin_length.get() {
+                    return Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::InvalidParameter,
+                        None,
+                    )));
+                }
+                self.min_pin_length.set(*new_min_pin_length);
+                Ok(())
+            }
+        }
+    }
+
     fn check_key_handle(&self, _req: &CheckKeyHandle) -> Result<(), HIDError> {
         Err(HIDError::UnsupportedCommand)
     }
@@ -283,11 +656,12 @@ impl VirtualFidoDevice for TestToken {
                         None,
                     )));
                 }
+                self.perform_internal_uv()?;
                 let secret = match self.shared_secret.as_ref() {
                     Some(secret) => secret,
                     _ => return Err(HIDError::DeviceError),
                 };
-                let encrypted_pin_token = match secret.encrypt(&self.pin_token) {
+                let encrypted_pin_token = match secret.encrypt(&self.pin_token.get()) {
                     Ok(token) => token,
                     _ => return Err(HIDError::DeviceError),
                 };
@@ -296,6 +670,355 @@ impl VirtualFidoDevice for TestToken {
                     ..Default::default()
                 })
             }
+            PINSubcommand::SetPin => {
+                let secret = self.shared_secret.as_ref().ok_or(HIDError::DeviceError)?;
+                let new_pin_enc = req.new_pin_enc.as_ref().ok_or(HIDError::DeviceError)?;
+                let pin_uv_auth_param =
+                    req.pin_uv_auth_param.as_ref().ok_or(HIDError::DeviceError)?;
+
+                if secret.authenticate(new_pin_enc) != *pin_uv_auth_param {
+                    return Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::PinAuthInvalid,
+                        None,
+                    )));
+                }
+
+                if self.pin_hash.borrow().is_some() {
+                    // SetPin may only be used to provision an authenticator that has no PIN yet;
+                    // ChangePin is used to change an existing one.
+                    return Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::NotAllowed,
+                        None,
+                    )));
+                }
+
+                let padded_pin = secret.decrypt(new_pin_enc).map_err(|_| HIDError::DeviceError)?;
+                let pin = Self::unpad_pin(&padded_pin);
+                if pin.len() < self.min_pin_length.get() as usize {
+                    return Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::PinPolicyViolation,
+                        None,
+                    )));
+                }
+
+                *self.pin_hash.borrow_mut() = Some(Self::pin_hash_of(pin));
+                Ok(ClientPinResponse::default())
+            }
+            PINSubcommand::ChangePin => {
+                let secret = self.shared_secret.as_ref().ok_or(HIDError::DeviceError)?;
+                let new_pin_enc = req.new_pin_enc.as_ref().ok_or(HIDError::DeviceError)?;
+                let pin_hash_enc = req.pin_hash_enc.as_ref().ok_or(HIDError::DeviceError)?;
+                let pin_uv_auth_param =
+                    req.pin_uv_auth_param.as_ref().ok_or(HIDError::DeviceError)?;
+
+                let mut authenticated_message = new_pin_enc.clone();
+                authenticated_message.extend_from_slice(pin_hash_enc);
+                if secret.authenticate(&authenticated_message) != *pin_uv_auth_param {
+                    return Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::PinAuthInvalid,
+                        None,
+                    )));
+                }
+
+                self.check_pin_hash_enc(secret, pin_hash_enc)?;
+
+                let padded_pin = secret.decrypt(new_pin_enc).map_err(|_| HIDError::DeviceError)?;
+                let pin = Self::unpad_pin(&padded_pin);
+                if pin.len() < self.min_pin_length.get() as usize {
+                    return Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::PinPolicyViolation,
+                        None,
+                    )));
+                }
+
+                *self.pin_hash.borrow_mut() = Some(Self::pin_hash_of(pin));
+                Ok(ClientPinResponse::default())
+            }
+            PINSubcommand::GetPinToken
+            | PINSubcommand::GetPinUvAuthTokenUsingPinWithPermissions => {
+                let secret = self.shared_secret.as_ref().ok_or(HIDError::DeviceError)?;
+                let pin_hash_enc = req.pin_hash_enc.as_ref().ok_or(HIDError::DeviceError)?;
+
+                self.check_pin_hash_enc(secret, pin_hash_enc)?;
+
+                let encrypted_pin_token =
+                    secret.encrypt(&self.pin_token.get()).map_err(|_| HIDError::DeviceError)?;
+                Ok(ClientPinResponse {
+                    pin_token: Some(encrypted_pin_token),
+                    ..Default::default()
+                })
+            }
+            _ => Err(HIDError::UnsupportedCommand),
+        }
+    }
+
+    /// Implements the CTAP2.1 `authenticatorCredentialManagement` command (6.8): every
+    /// subcommand below is handled, with `UpdateUserInformation` further restricted to
+    /// discoverable credentials per 6.8.5. The pinUvAuthParam is only checked for presence, not
+    /// authenticated. `VirtualFidoDevice` is the authenticator crate's single
+    /// trait-method-per-command surface, so `req`/the returned response play the role a
+    /// bespoke request/result pair would in a from-scratch implementation.
+    fn credential_management(
+        &self,
+        req: &CredentialManagement,
+    ) -> Result<CredentialManagementResponse, HIDError> {
+        // TODO: authenticate pinUvAuthParam over the exact CBOR-encoded subCommandParams; for now
+        // we only require that one was supplied, as `authenticator_config` and `bio_enrollment`
+        // also do.
+        if req.pin_uv_auth_param.is_none() {
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::PinRequired,
+                None,
+            )));
+        }
+
+        match req.subcommand {
+            CredentialManagementSubCommand::GetCredsMetadata => {
+                let credentials = self.credentials.borrow();
+                let existing = credentials.iter().filter(|c| c.is_discoverable_credential).count();
+                Ok(CredentialManagementResponse {
+                    existing_resident_credentials_count: Some(existing as u64),
+                    max_possible_remaining_resident_credentials_count: Some(
+                        (MAX_RESIDENT_CREDENTIALS - existing.min(MAX_RESIDENT_CREDENTIALS)) as u64,
+                    ),
+                    ..Default::default()
+                })
+            }
+            CredentialManagementSubCommand::EnumerateRPsBegin => {
+                let credentials = self.credentials.borrow();
+                let mut rps: Vec<RelyingPartyWrapper> = vec![];
+                for credential in credentials.iter().filter(|c| c.is_discoverable_credential) {
+                    if !rps.iter().any(|rp| rp.hash() == credential.rp.hash()) {
+                        rps.push(credential.rp.clone());
+                    }
+                }
+                if rps.is_empty() {
+                    return Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::NoCredentials,
+                        None,
+                    )));
+                }
+
+                let total_rps = rps.len() as u64;
+                let first = rps[0].clone();
+                *self.rp_enumeration.borrow_mut() = Some(RpEnumerationCursor { rps, next: 1 });
+                Ok(CredentialManagementResponse {
+                    rp_id_hash: Some(first.hash()),
+                    rp: Some(first),
+                    total_rps: Some(total_rps),
+                    ..Default::default()
+                })
+            }
+            CredentialManagementSubCommand::EnumerateRPsGetNextRP => {
+                let mut cursor = self.rp_enumeration.borrow_mut();
+                let state = cursor.as_mut().ok_or(HIDError::Command(CommandError::StatusCode(
+                    StatusCode::NotAllowed,
+                    None,
+                )))?;
+                let rp = state
+                    .rps
+                    .get(state.next)
+                    .cloned()
+                    .ok_or(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::NotAllowed,
+                        None,
+                    )))?;
+                state.next += 1;
+                Ok(CredentialManagementResponse {
+                    rp_id_hash: Some(rp.hash()),
+                    rp: Some(rp),
+                    ..Default::default()
+                })
+            }
+            CredentialManagementSubCommand::EnumerateCredentialsBegin => {
+                let rp_id_hash = req
+                    .rp_id_hash
+                    .as_ref()
+                    .ok_or(HIDError::DeviceError)?
+                    .clone();
+                let credentials = self.credentials.borrow();
+                let matching: Vec<&TestTokenCredential> = credentials
+                    .iter()
+                    .filter(|c| c.is_discoverable_credential && c.rp.hash() == rp_id_hash)
+                    .collect();
+                if matching.is_empty() {
+                    return Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::NoCredentials,
+                        None,
+                    )));
+                }
+
+                let total_credentials = matching.len() as u64;
+                let credential_ids: Vec<Vec<u8>> =
+                    matching.iter().map(|c| c.id.clone()).collect();
+                let response = Self::credential_entry_response(matching[0], Some(total_credentials));
+                *self.cred_enumeration.borrow_mut() = Some(CredEnumerationCursor {
+                    rp_id_hash,
+                    credential_ids,
+                    next: 1,
+                });
+                Ok(response)
+            }
+            CredentialManagementSubCommand::EnumerateCredentialsGetNextCredential => {
+                let next_id = {
+                    let mut cursor = self.cred_enumeration.borrow_mut();
+                    let state =
+                        cursor.as_mut().ok_or(HIDError::Command(CommandError::StatusCode(
+                            StatusCode::NotAllowed,
+                            None,
+                        )))?;
+                    let id = state.credential_ids.get(state.next).cloned().ok_or(
+                        HIDError::Command(CommandError::StatusCode(StatusCode::NotAllowed, None)),
+                    )?;
+                    state.next += 1;
+                    id
+                };
+                let credentials = self.credentials.borrow();
+                let credential = credentials
+                    .iter()
+                    .find(|c| c.id == next_id)
+                    .ok_or(HIDError::DeviceError)?;
+                Ok(Self::credential_entry_response(credential, None))
+            }
+            CredentialManagementSubCommand::DeleteCredential => {
+                let credential_id = req
+                    .credential_id
+                    .as_ref()
+                    .ok_or(HIDError::DeviceError)?
+                    .id
+                    .clone();
+                let mut credentials = self.credentials.borrow_mut();
+                match credentials.binary_search_by_key(&credential_id.as_slice(), |c| &c.id) {
+                    Ok(idx) => {
+                        credentials.remove(idx);
+                        Ok(CredentialManagementResponse::default())
+                    }
+                    Err(_) => Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::NoCredentials,
+                        None,
+                    ))),
+                }
+            }
+            CredentialManagementSubCommand::UpdateUserInformation => {
+                // CTAP2.1 6.8.5: only discoverable credentials carry user information the
+                // platform can update.
+                let credential_id = req
+                    .credential_id
+                    .as_ref()
+                    .ok_or(HIDError::DeviceError)?
+                    .id
+                    .clone();
+                let user = req.user.as_ref().ok_or(HIDError::DeviceError)?;
+                let credentials = self.credentials.borrow();
+                let idx = credentials
+                    .binary_search_by_key(&credential_id.as_slice(), |c| &c.id)
+                    .map_err(|_| {
+                        HIDError::Command(CommandError::StatusCode(StatusCode::NoCredentials, None))
+                    })?;
+                if !credentials[idx].is_discoverable_credential {
+                    return Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::NoCredentials,
+                        None,
+                    )));
+                }
+                drop(credentials);
+                self.credentials.borrow_mut()[idx].user_handle = user.id.clone();
+                Ok(CredentialManagementResponse::default())
+            }
+            _ => Err(HIDError::UnsupportedCommand),
+        }
+    }
+
+    fn bio_enrollment(&self, req: &BioEnrollment) -> Result<BioEnrollmentResponse, HIDError> {
+        // GetFingerprintSensorInfo is the only subcommand that doesn't act on an existing
+        // enrollment and is allowed without a pinUvAuthParam, mirroring how real sensors report
+        // their capabilities before any UV ceremony has happened.
+        if req.subcommand != BioEnrollmentSubCommand::GetFingerprintSensorInfo
+            && req.pin_uv_auth_param.is_none()
+        {
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::PinRequired,
+                None,
+            )));
+        }
+
+        match req.subcommand {
+            BioEnrollmentSubCommand::GetFingerprintSensorInfo => Ok(BioEnrollmentResponse {
+                modality: Some(1), // fingerprint
+                max_capture_samples_required_for_enroll: Some(1),
+                max_template_friendly_name: Some(32),
+                ..Default::default()
+            }),
+            BioEnrollmentSubCommand::EnrollBegin => {
+                self.perform_internal_uv()?;
+                let mut template_id = vec![0u8; 16];
+                thread_rng().fill_bytes(&mut template_id);
+                self.bio_templates.borrow_mut().push(BioTemplate {
+                    template_id: template_id.clone(),
+                    friendly_name: None,
+                });
+                Ok(BioEnrollmentResponse {
+                    template_id: Some(template_id),
+                    last_enroll_sample_status: Some(0), // good sample, enrollment complete
+                    remaining_samples: Some(0),
+                    ..Default::default()
+                })
+            }
+            BioEnrollmentSubCommand::EnrollCaptureNextSample => {
+                self.perform_internal_uv()?;
+                Ok(BioEnrollmentResponse {
+                    template_id: req.template_id.clone(),
+                    last_enroll_sample_status: Some(0),
+                    remaining_samples: Some(0),
+                    ..Default::default()
+                })
+            }
+            BioEnrollmentSubCommand::CancelCurrentEnrollment => Ok(BioEnrollmentResponse::default()),
+            BioEnrollmentSubCommand::EnumerateEnrollments => {
+                let templates = self.bio_templates.borrow();
+                if templates.is_empty() {
+                    return Err(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::InvalidOption,
+                        None,
+                    )));
+                }
+                Ok(BioEnrollmentResponse {
+                    template_infos: Some(
+                        templates
+                            .iter()
+                            .map(|t| (t.template_id.clone(), t.friendly_name.clone()))
+                            .collect(),
+                    ),
+                    ..Default::default()
+                })
+            }
+            BioEnrollmentSubCommand::SetFriendlyName => {
+                let template_id = req.template_id.as_ref().ok_or(HIDError::DeviceError)?;
+                let friendly_name = req.template_friendly_name.clone().ok_or(HIDError::DeviceError)?;
+                let mut templates = self.bio_templates.borrow_mut();
+                let template = templates
+                    .iter_mut()
+                    .find(|t| &t.template_id == template_id)
+                    .ok_or(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::InvalidOption,
+                        None,
+                    )))?;
+                template.friendly_name = Some(friendly_name);
+                Ok(BioEnrollmentResponse::default())
+            }
+            BioEnrollmentSubCommand::RemoveEnrollment => {
+                let template_id = req.template_id.as_ref().ok_or(HIDError::DeviceError)?;
+                let mut templates = self.bio_templates.borrow_mut();
+                let idx = templates
+                    .iter()
+                    .position(|t| &t.template_id == template_id)
+                    .ok_or(HIDError::Command(CommandError::StatusCode(
+                        StatusCode::InvalidOption,
+                        None,
+                    )))?;
+                templates.remove(idx);
+                Ok(BioEnrollmentResponse::default())
+            }
             _ => Err(HIDError::UnsupportedCommand),
         }
     }
@@ -308,7 +1031,14 @@ impl VirtualFidoDevice for TestToken {
         // (not implemented)
 
         // 2. Validate pinUvAuthParam
-        // Handled by caller
+        if let Some(pin_uv_auth_param) = &req.pin_uv_auth_param {
+            if !self.verify_pin_uv_auth_param(req.client_data_hash.as_ref(), pin_uv_auth_param) {
+                return Err(HIDError::Command(CommandError::StatusCode(
+                    StatusCode::PinAuthInvalid,
+                    None,
+                )));
+            }
+        }
 
         // 3. Initialize "uv" and "up" bits to false
         let mut flags = AuthenticatorDataFlags::empty();
@@ -333,11 +1063,19 @@ impl VirtualFidoDevice for TestToken {
         let effective_up_opt = req.options.user_presence.unwrap_or(true);
 
         // 5. alwaysUv
-        // (not implemented)
+        if self.always_uv.get() && req.pin_uv_auth_param.is_none() {
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::PinRequired,
+                None,
+            )));
+        }
 
         // 6. User verification
-        // TODO: Permissions, (maybe) validate pinUvAuthParam
-        if self.is_user_verified && (effective_uv_opt || req.pin_uv_auth_param.is_some()) {
+        // TODO: Permissions
+        if self.is_user_verified && effective_uv_opt {
+            self.perform_internal_uv()?;
+            flags |= AuthenticatorDataFlags::USER_VERIFIED;
+        } else if self.is_user_verified && req.pin_uv_auth_param.is_some() {
             flags |= AuthenticatorDataFlags::USER_VERIFIED;
         }
 
@@ -355,35 +1093,98 @@ impl VirtualFidoDevice for TestToken {
         }
 
         // 10. Extensions
-        // (not implemented)
+        // hmac-secret is the only extension we answer; it's computed per matched credential
+        // below since the output depends on that credential's stored CredRandom.
 
+        let uv_performed = flags.contains(AuthenticatorDataFlags::USER_VERIFIED);
+
+        self.assertion_queue.borrow_mut().clear();
         let mut assertions: Vec<Assertion> = vec![];
         if !req.allow_list.is_empty() {
             // 11. Non-discoverable credential case
             // return at most one assertion matching an allowed credential ID
+            let mut found_protected = false;
             for credential in eligible_cred_iter {
                 if req.allow_list.iter().any(|x| x.id == credential.id) {
-                    let assertion = credential.assert(&req.client_data_hash, flags).into();
+                    // The ID was explicitly named in allow_list, so credProtect's
+                    // userVerificationOptionalWithCredentialIDList is always satisfied here.
+                    if !Self::credential_disclosure_allowed(credential, uv_performed, true) {
+                        found_protected = true;
+                        continue;
+                    }
+                    let extensions = self.build_assertion_extensions(credential, flags, req)?;
+                    let cred_flags = if extensions.has_some() {
+                        flags | AuthenticatorDataFlags::EXTENSION_DATA
+                    } else {
+                        flags
+                    };
+                    let assertion = credential
+                        .assert(&req.client_data_hash, cred_flags, None, extensions)
+                        .into();
                     assertions.push(assertion);
                     break;
                 }
             }
+            if assertions.is_empty() && found_protected {
+                return Err(HIDError::Command(CommandError::StatusCode(
+                    StatusCode::NotAllowed,
+                    None,
+                )));
+            }
         } else {
             // 12. Discoverable credential case
-            // return any number of assertions from credentials bound to this RP ID
-            // TODO(Bug 1838932) Until we have conditional mediation we actually don't want to
-            // return a list of credentials here. The UI to select one of the results blocks
-            // testing.
-            for credential in eligible_cred_iter.filter(|x| x.is_discoverable_credential) {
-                let assertion = credential.assert(&req.client_data_hash, flags).into();
+            // return an assertion for every discoverable credential bound to this RP ID; the
+            // first is returned here and the rest are handed out one at a time via
+            // `authenticatorGetNextAssertion`.
+            let matching: Vec<_> = eligible_cred_iter
+                .filter(|x| x.is_discoverable_credential)
+                .filter(|x| Self::credential_disclosure_allowed(x, uv_performed, false))
+                .collect();
+            let count = matching.len();
+            let mut iter = matching.into_iter();
+            if let Some(first) = iter.next() {
+                let number_of_credentials = if count > 1 { Some(count as u64) } else { None };
+                let extensions = self.build_assertion_extensions(first, flags, req)?;
+                let first_flags = if extensions.has_some() {
+                    flags | AuthenticatorDataFlags::EXTENSION_DATA
+                } else {
+                    flags
+                };
+                let assertion = first
+                    .assert(&req.client_data_hash, first_flags, number_of_credentials, extensions)
+                    .into();
                 assertions.push(assertion);
-                break;
+
+                let mut queue = self.assertion_queue.borrow_mut();
+                for credential in iter {
+                    let extensions = self.build_assertion_extensions(credential, flags, req)?;
+                    let cred_flags = if extensions.has_some() {
+                        flags | AuthenticatorDataFlags::EXTENSION_DATA
+                    } else {
+                        flags
+                    };
+                    queue.push_back(
+                        credential
+                            .assert(&req.client_data_hash, cred_flags, None, extensions)
+                            .into(),
+                    );
+                }
             }
         }
 
         Ok(GetAssertionResult(assertions))
     }
 
+    fn get_next_assertion(&self) -> Result<GetAssertionResult, HIDError> {
+        match self.assertion_queue.borrow_mut().pop_front() {
+            Some(assertion) => Ok(GetAssertionResult(vec![assertion])),
+            None => Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::NotAllowed,
+                None,
+            ))),
+        }
+    }
+
     fn get_info(&self) -> Result<AuthenticatorInfo, HIDError> {
         // This is a CTAP2.1 device with internal user verification support
         Ok(AuthenticatorInfo {
@@ -391,8 +1192,11 @@ impl VirtualFidoDevice for TestToken {
             options: AuthenticatorOptions {
                 pin_uv_auth_token: Some(true),
                 user_verification: Some(true),
+                always_uv: Some(self.always_uv.get()),
+                ep: Some(self.enterprise_attestation_enabled.get()),
                 ..Default::default()
             },
+            min_pin_length: Some(self.min_pin_length.get() as u64),
             ..Default::default()
         })
     }
@@ -409,19 +1213,28 @@ impl VirtualFidoDevice for TestToken {
         // (not implemented)
 
         // 2. Validate pinUvAuthParam
-        // Handled by caller
+        if let Some(pin_uv_auth_param) = &req.pin_uv_auth_param {
+            if !self.verify_pin_uv_auth_param(req.client_data_hash.as_ref(), pin_uv_auth_param) {
+                return Err(HIDError::Command(CommandError::StatusCode(
+                    StatusCode::PinAuthInvalid,
+                    None,
+                )));
+            }
+        }
 
         // 3. Validate pubKeyCredParams
-        if !req
-            .pub_cred_params
+        //
+        // Pick the first algorithm we support, in our own preference order, that the RP also
+        // lists; reject only if none match.
+        let alg = self
+            .supported_algorithms
             .iter()
-            .any(|x| x.alg == COSEAlgorithm::ES256)
-        {
-            return Err(HIDError::Command(CommandError::StatusCode(
+            .find(|alg| req.pub_cred_params.iter().any(|x| x.alg == **alg))
+            .copied()
+            .ok_or(HIDError::Command(CommandError::StatusCode(
                 StatusCode::UnsupportedAlgorithm,
                 None,
-            )));
-        }
+            )))?;
 
         // 4. initialize "uv" and "up" bits to false
         let mut flags = AuthenticatorDataFlags::empty();
@@ -452,17 +1265,33 @@ impl VirtualFidoDevice for TestToken {
         // Nothing to do. We don't provide a way to set up=false.
 
         // 6. alwaysUv option ID
-        // (not implemented)
+        if self.always_uv.get() && req.pin_uv_auth_param.is_none() {
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::PinRequired,
+                None,
+            )));
+        }
 
         // 7. and 8. makeCredUvNotRqd option ID
         // (not implemented)
 
         // 9. enterprise attestation
-        // (not implemented)
+        // CTAP2.1 6.1.2 step 9: enterprise attestation may only be requested if it was enabled
+        // via authenticatorConfig's enableEnterpriseAttestation.
+        let enterprise_attestation_requested = req.enterprise_attestation.is_some();
+        if enterprise_attestation_requested && !self.enterprise_attestation_enabled.get() {
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::InvalidOption,
+                None,
+            )));
+        }
 
         // 11. User verification
-        // TODO: Permissions, (maybe) validate pinUvAuthParam
-        if self.is_user_verified {
+        // TODO: Permissions
+        if self.is_user_verified && effective_uv_opt {
+            self.perform_internal_uv()?;
+            flags |= AuthenticatorDataFlags::USER_VERIFIED;
+        } else if self.is_user_verified {
             flags |= AuthenticatorDataFlags::USER_VERIFIED;
         }
 
@@ -484,11 +1313,22 @@ impl VirtualFidoDevice for TestToken {
         }
 
         // 15. process extensions
-        // (not implemented)
+        let hmac_secret_cred_random = if req.extensions.hmac_secret.unwrap_or(false) {
+            let mut with_uv = [0u8; 32];
+            let mut without_uv = [0u8; 32];
+            thread_rng().fill_bytes(&mut with_uv);
+            thread_rng().fill_bytes(&mut without_uv);
+            Some(HmacSecretCredRandom {
+                with_uv,
+                without_uv,
+            })
+        } else {
+            None
+        };
+        let cred_protect = req.extensions.cred_protect;
 
         // 16. Generate a new credential.
-        let (private, public) =
-            COSEKey::generate(COSEAlgorithm::ES256).map_err(|_| HIDError::DeviceError)?;
+        let (private, public) = COSEKey::generate(alg).map_err(|_| HIDError::DeviceError)?;
         let counter = 0;
 
         // 17. and 18. Store credential
@@ -501,36 +1341,77 @@ impl VirtualFidoDevice for TestToken {
         self.insert_credential(
             &id,
             &private,
+            alg,
             &req.rp,
             req.options.resident_key.unwrap_or(false),
             &req.user.clone().unwrap_or_default().id,
             counter,
+            hmac_secret_cred_random,
+            cred_protect,
         );
 
         // 19. Generate attestation statement
         flags |= AuthenticatorDataFlags::ATTESTED;
 
+        let extensions = Extension {
+            hmac_secret: hmac_secret_cred_random.map(|_| HmacSecretResponse::Confirmed(true)),
+            cred_protect,
+            ..Default::default()
+        };
+        if extensions.has_some() {
+            flags |= AuthenticatorDataFlags::EXTENSION_DATA;
+        }
+
         let auth_data = AuthenticatorData {
             rp_id_hash: req.rp.hash(),
             flags,
             counter,
             credential_data: Some(AttestedCredentialData {
-                aaguid: VIRTUAL_TOKEN_AAGUID,
+                aaguid: self.aaguid.clone(),
                 credential_id: id.to_vec(),
                 credential_public_key: public,
             }),
-            extensions: Extension::default(),
+            extensions,
         };
 
         let mut data = auth_data.to_vec().unwrap();
         data.extend_from_slice(req.client_data_hash.as_ref());
-        let sig = ecdsa_p256_sha256_sign_raw(&private, &data).unwrap();
 
-        let att_statement = AttestationStatement::Packed(AttestationStatementPacked {
-            alg: COSEAlgorithm::ES256,
-            sig: sig.as_slice().into(),
-            attestation_cert: vec![],
-        });
+        // Enterprise attestation exists to let the RP identify this specific authenticator, so
+        // granting it overrides a `None` attestation type into self attestation; `self.aaguid`
+        // (`VIRTUAL_TOKEN_AAGUID` unless the caller of `TestTokenManager::add_virtual_authenticator`
+        // supplied one) is used for the credential data above regardless of which branch we take.
+        let effective_attestation_type = if enterprise_attestation_requested {
+            match self.attestation_type.get() {
+                AttestationType::None => AttestationType::SelfAttestation,
+                other => other,
+            }
+        } else {
+            self.attestation_type.get()
+        };
+
+        let att_statement = match effective_attestation_type {
+            AttestationType::None => AttestationStatement::None,
+            AttestationType::SelfAttestation => {
+                let sig = sign_with(alg, &private, &data);
+                AttestationStatement::Packed(AttestationStatementPacked {
+                    alg,
+                    sig: sig.as_slice().into(),
+                    attestation_cert: vec![],
+                })
+            }
+            // `AttCA` doesn't change anything about what we generate here; the distinction is
+            // only about how the RP should interpret the certificate chain we hand back.
+            AttestationType::Basic | AttestationType::AttCA => {
+                let batch = self.batch_attestation.as_ref().ok_or(HIDError::DeviceError)?;
+                let sig = ecdsa_p256_sha256_sign_raw(&batch.private_key, &data).unwrap();
+                AttestationStatement::Packed(AttestationStatementPacked {
+                    alg: COSEAlgorithm::ES256,
+                    sig: sig.as_slice().into(),
+                    attestation_cert: vec![batch.certificate.clone()],
+                })
+            }
+        };
 
         let result = MakeCredentialsResult(AttestationObject {
             auth_data,
@@ -540,11 +1421,46 @@ impl VirtualFidoDevice for TestToken {
     }
 
     fn reset(&self, _req: &Reset) -> Result<(), HIDError> {
-        Err(HIDError::UnsupportedCommand)
+        // CTAP2.1 6.6: requires user presence, and wipes all state the authenticator holds.
+        if !self.is_user_consenting {
+            return Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::OperationDenied,
+                None,
+            )));
+        }
+
+        self.credentials.borrow_mut().clear();
+        *self.pin_hash.borrow_mut() = None;
+        self.pin_retries.set(MAX_PIN_RETRIES);
+
+        let mut new_pin_token = [0u8; 32];
+        thread_rng().fill_bytes(&mut new_pin_token);
+        self.pin_token.set(new_pin_token);
+
+        *self.rp_enumeration.borrow_mut() = None;
+        *self.cred_enumeration.borrow_mut() = None;
+        self.assertion_queue.borrow_mut().clear();
+
+        self.always_uv.set(false);
+        self.min_pin_length.set(DEFAULT_MIN_PIN_LENGTH);
+        self.enterprise_attestation_enabled.set(false);
+
+        self.bio_templates.borrow_mut().clear();
+        self.uv_retries.set(MAX_UV_RETRIES);
+
+        Ok(())
     }
 
     fn selection(&self, _req: &Selection) -> Result<(), HIDError> {
-        Err(HIDError::UnsupportedCommand)
+        // CTAP2.1 6.9: waits for, and reports, user presence.
+        if self.is_user_consenting {
+            Ok(())
+        } else {
+            Err(HIDError::Command(CommandError::StatusCode(
+                StatusCode::OperationDenied,
+                None,
+            )))
+        }
     }
 }
 
@@ -590,6 +1506,11 @@ impl CredentialParameters {
     }
 }
 
+/// Backs the WebDriver "Virtual Authenticators" extension
+/// (https://w3c.github.io/webauthn/#sctn-automation): `add_credential`, `get_credentials`, and
+/// `remove_credential`/`remove_all_credentials` below implement the spec's addCredential,
+/// getCredentials, and removeCredential/removeAllCredentials commands, respectively, against
+/// whichever `authenticator_id` was returned by `add_virtual_authenticator`.
 #[derive(Default)]
 pub(crate) struct TestTokenManager {
     state: Mutex<HashMap<u64, TestToken>>,
@@ -607,6 +1528,10 @@ impl TestTokenManager {
         has_user_verification: bool,
         is_user_consenting: bool,
         is_user_verified: bool,
+        supported_algorithms: Vec<COSEAlgorithm>,
+        aaguid: Option<AAGuid>,
+        attestation_type: AttestationType,
+        batch_attestation: Option<(Vec<u8>, Vec<u8>)>,
     ) -> Result<u64, nsresult> {
         let mut guard = self.state.lock().map_err(|_| NS_ERROR_FAILURE)?;
         let token = TestToken::new(
@@ -615,6 +1540,13 @@ impl TestTokenManager {
             has_user_verification,
             is_user_consenting,
             is_user_verified,
+            supported_algorithms,
+            aaguid.unwrap_or(VIRTUAL_TOKEN_AAGUID),
+            attestation_type,
+            batch_attestation.map(|(certificate, private_key)| BatchAttestation {
+                certificate,
+                private_key,
+            }),
         );
         loop {
             let id = rand::random::<u64>() & 0x1f_ffff_ffff_ffffu64; // Make the id safe for JS (53 bits)
@@ -642,7 +1574,9 @@ impl TestTokenManager {
         authenticator_id: u64,
         id: &[u8],
         privkey: &[u8],
-        user_handle: &[u8],
+        // The WebDriver "Add Credential" command allows omitting the user handle for
+        // non-resident credentials.
+        user_handle: Option<&[u8]>,
         sign_count: u32,
         rp_id: String,
         is_resident_credential: bool,
@@ -660,10 +1594,14 @@ impl TestTokenManager {
         token.insert_credential(
             id,
             privkey,
+            // The WebDriver virtual authenticator API only ever injects raw P-256 keys.
+            COSEAlgorithm::ES256,
             &RelyingPartyWrapper::Data(rp),
             is_resident_credential,
-            user_handle,
+            user_handle.unwrap_or(&[]),
             sign_count,
+            None,
+            None,
         );
         Ok(())
     }
@@ -737,6 +1675,22 @@ impl TestTokenManager {
         Ok(())
     }
 
+    /// Sets whether the next simulated biometric sample (an enrollment capture or an
+    /// internal-UV ceremony) matches, for tests exercising a rejected fingerprint.
+    pub fn set_next_uv_sample_matches(
+        &self,
+        authenticator_id: u64,
+        matches: bool,
+    ) -> Result<(), nsresult> {
+        let mut guard = self.state.lock().map_err(|_| NS_ERROR_FAILURE)?;
+        let token = guard
+            .deref_mut()
+            .get_mut(&authenticator_id)
+            .ok_or(NS_ERROR_INVALID_ARG)?;
+        token.next_uv_sample_matches.set(matches);
+        Ok(())
+    }
+
     pub fn register(
         &self,
         _timeout: u64,
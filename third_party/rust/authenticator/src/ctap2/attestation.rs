@@ -66,21 +66,250 @@ impl<'de> Deserialize<'de> for HmacSecretResponse {
     }
 }
 
+/// The platform side of CTAP2 PIN/UV Auth Protocol 1 key agreement
+/// (https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#pinProto1).
+/// `sharedSecret = SHA-256(Z_x)`, where `Z_x` is the x-coordinate of the ECDH point computed
+/// from the authenticator's `keyAgreement` key and an ephemeral platform keypair.
+pub struct PinUvSharedSecret {
+    key: [u8; 32],
+}
+
+impl PinUvSharedSecret {
+    /// Generates an ephemeral P-256 keypair, performs ECDH with `authenticator_key_agreement`,
+    /// and returns the derived shared secret along with the ephemeral public key the platform
+    /// sends back to the authenticator as its own `keyAgreement`.
+    pub fn new(
+        authenticator_key_agreement: &COSEKey,
+    ) -> Result<(Self, COSEKey), AuthenticatorError> {
+        let (ephemeral_private, ephemeral_public) =
+            COSEKey::generate(COSEAlgorithm::ECDH_ES_HKDF256)
+                .map_err(|e| AuthenticatorError::InternalError(format!("{e:?}")))?;
+        let z_x = crate::crypto::ecdh_p256_raw(&ephemeral_private, authenticator_key_agreement)
+            .map_err(|e| AuthenticatorError::InternalError(format!("{e:?}")))?;
+        Ok((
+            PinUvSharedSecret {
+                key: crate::crypto::sha256(&z_x),
+            },
+            ephemeral_public,
+        ))
+    }
+
+    /// `authenticate(message) = LEFT(16, HMAC-SHA-256(sharedSecret, message))`
+    fn authenticate(&self, message: &[u8]) -> [u8; 16] {
+        let mac = crate::crypto::hmac_sha256(&self.key, message);
+        let mut left16 = [0u8; 16];
+        left16.copy_from_slice(&mac[..16]);
+        left16
+    }
+
+    /// AES-256-CBC(sharedSecret, IV=0, plaintext), no padding.
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, AuthenticatorError> {
+        crate::crypto::aes256_cbc_encrypt_zero_iv(&self.key, plaintext)
+            .map_err(|e| AuthenticatorError::InternalError(format!("{e:?}")))
+    }
+
+    /// AES-256-CBC(sharedSecret, IV=0, ciphertext)^-1, no padding.
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, AuthenticatorError> {
+        crate::crypto::aes256_cbc_decrypt_zero_iv(&self.key, ciphertext)
+            .map_err(|e| AuthenticatorError::InternalError(format!("{e:?}")))
+    }
+}
+
+/// The platform-to-authenticator side of the `hmac-secret` extension
+/// (https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-hmac-secret-extension),
+/// carried as the `hmac-secret` key of the `extensions` map on `GetAssertion` requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HmacSecretInput {
+    /// The platform's ephemeral `keyAgreement` key, as returned by `PinUvSharedSecret::new`.
+    pub key_agreement: COSEKey,
+    pub salt_enc: Vec<u8>,
+    pub salt_auth: Vec<u8>,
+}
+
+impl HmacSecretInput {
+    /// Builds the `hmac-secret` extension input for one or two 32-byte salts.
+    pub fn new(
+        key_agreement: COSEKey,
+        shared_secret: &PinUvSharedSecret,
+        salt1: [u8; 32],
+        salt2: Option<[u8; 32]>,
+    ) -> Result<Self, AuthenticatorError> {
+        let mut salts = salt1.to_vec();
+        if let Some(salt2) = salt2 {
+            salts.extend_from_slice(&salt2);
+        }
+        let salt_enc = shared_secret.encrypt(&salts)?;
+        let salt_auth = shared_secret.authenticate(&salt_enc).to_vec();
+        Ok(HmacSecretInput {
+            key_agreement,
+            salt_enc,
+            salt_auth,
+        })
+    }
+}
+
+impl Serialize for HmacSecretInput {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry(&1, &self.key_agreement)?;
+        map.serialize_entry(&2, serde_bytes::Bytes::new(&self.salt_enc))?;
+        map.serialize_entry(&3, serde_bytes::Bytes::new(&self.salt_auth))?;
+        map.end()
+    }
+}
+
+impl HmacSecretResponse {
+    /// Decrypts the one or two 32-byte (or 64-byte combined) HMAC outputs carried by a
+    /// `GetAssertion` response. `Confirmed` carries no ciphertext (it only appears on
+    /// `MakeCredential` responses), so it decrypts to an empty vector.
+    pub fn decrypt_secret(
+        &self,
+        shared_secret: &PinUvSharedSecret,
+    ) -> Result<Vec<u8>, AuthenticatorError> {
+        match self {
+            HmacSecretResponse::Confirmed(_) => Ok(vec![]),
+            HmacSecretResponse::Secret(ciphertext) => shared_secret.decrypt(ciphertext),
+        }
+    }
+}
+
+/// The `credProtect` extension's protection level, as a CTAP2 `credentialProtectionPolicy`
+/// unsigned integer (1-3). See
+/// https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-credProtect-extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialProtectionPolicy {
+    UserVerificationOptional,
+    UserVerificationOptionalWithCredentialIDList,
+    UserVerificationRequired,
+}
+
+impl Serialize for CredentialProtectionPolicy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let value: u8 = match self {
+            CredentialProtectionPolicy::UserVerificationOptional => 1,
+            CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList => 2,
+            CredentialProtectionPolicy::UserVerificationRequired => 3,
+        };
+        serializer.serialize_u8(value)
+    }
+}
+
+impl<'de> Deserialize<'de> for CredentialProtectionPolicy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CredentialProtectionPolicyVisitor;
+
+        impl<'de> Visitor<'de> for CredentialProtectionPolicyVisitor {
+            type Value = CredentialProtectionPolicy;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an integer from 1 to 3")
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: SerdeError,
+            {
+                match v {
+                    1 => Ok(CredentialProtectionPolicy::UserVerificationOptional),
+                    2 => Ok(CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList),
+                    3 => Ok(CredentialProtectionPolicy::UserVerificationRequired),
+                    _ => Err(E::custom(format!("invalid credProtect value: {v}"))),
+                }
+            }
+        }
+
+        deserializer.deserialize_u64(CredentialProtectionPolicyVisitor)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CredBlobResponse {
+    /// Returned by MakeCredential calls to display whether the requested credBlob was stored.
+    Confirmed(bool),
+    /// Returned by GetAssertion: the stored credBlob bytes.
+    Data(Vec<u8>),
+}
+
+impl Serialize for CredBlobResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            CredBlobResponse::Confirmed(x) => serializer.serialize_bool(*x),
+            CredBlobResponse::Data(x) => serializer.serialize_bytes(x),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CredBlobResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CredBlobResponseVisitor;
+
+        impl<'de> Visitor<'de> for CredBlobResponseVisitor {
+            type Value = CredBlobResponse;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a byte array or a boolean")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: SerdeError,
+            {
+                Ok(CredBlobResponse::Data(v.to_vec()))
+            }
+
+            fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+            where
+                E: SerdeError,
+            {
+                Ok(CredBlobResponse::Confirmed(v))
+            }
+        }
+        deserializer.deserialize_any(CredBlobResponseVisitor)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub struct Extension {
-    #[serde(rename = "pinMinLength", skip_serializing_if = "Option::is_none")]
-    pub pin_min_length: Option<u64>,
+    #[serde(rename = "minPinLength", skip_serializing_if = "Option::is_none")]
+    pub min_pin_length: Option<u64>,
     #[serde(rename = "hmac-secret", skip_serializing_if = "Option::is_none")]
     pub hmac_secret: Option<HmacSecretResponse>,
+    #[serde(rename = "credProtect", skip_serializing_if = "Option::is_none")]
+    pub cred_protect: Option<CredentialProtectionPolicy>,
+    #[serde(rename = "credBlob", skip_serializing_if = "Option::is_none")]
+    pub cred_blob: Option<CredBlobResponse>,
+    /// Extension keys we don't have a dedicated field for, preserved so callers don't lose
+    /// data round-tripping through this type.
+    #[serde(flatten)]
+    pub unknown_keys: std::collections::BTreeMap<String, serde_cbor::Value>,
 }
 
 impl Extension {
-    fn has_some(&self) -> bool {
-        self.pin_min_length.is_some() || self.hmac_secret.is_some()
+    pub fn has_some(&self) -> bool {
+        self.min_pin_length.is_some()
+            || self.hmac_secret.is_some()
+            || self.cred_protect.is_some()
+            || self.cred_blob.is_some()
+            || !self.unknown_keys.is_empty()
     }
 }
 
-#[derive(Serialize, PartialEq, Default, Eq, Clone)]
+#[derive(Serialize, PartialEq, Default, Eq, Clone, PartialOrd, Ord, Hash)]
 pub struct AAGuid(pub [u8; 16]);
 
 impl AAGuid {
@@ -338,55 +567,548 @@ impl From<&[u8]> for Signature {
     }
 }
 
+// See https://w3c.github.io/webauthn/#defined-attestation-formats for the full list of formats.
 #[derive(Debug, PartialEq, Eq)]
 pub enum AttestationStatement {
     None,
     Packed(AttestationStatementPacked),
-    // TODO(baloo): there is a couple other options than None and Packed:
-    //              https://w3c.github.io/webauthn/#generating-an-attestation-object
-    //              https://w3c.github.io/webauthn/#defined-attestation-formats
-    //TPM,
-    //AndroidKey,
-    //AndroidSafetyNet,
     FidoU2F(AttestationStatementFidoU2F),
+    Tpm(AttestationStatementTpm),
+    AndroidKey(AttestationStatementAndroidKey),
+    AndroidSafetyNet(AttestationStatementAndroidSafetyNet),
+    Apple(AttestationStatementApple),
 }
 
-// Not all crypto-backends currently provide "crypto::verify()", so we do not implement it yet.
-// Also not sure, if we really need it. Would be a sanity-check only, to verify the signature is valid,
-// before sendig it out.
-// impl AttestationStatement {
-//     pub fn verify(&self, data: &[u8]) -> Result<bool, AuthenticatorError> {
-//         match self {
-//             AttestationStatement::None => Ok(true),
-//             AttestationStatement::Unparsed(_) => Err(AuthenticatorError::Custom(
-//                 "Unparsed attestation object can't be used to verify signature.".to_string(),
-//             )),
-//             AttestationStatement::FidoU2F(att) => {
-//                 let res = crypto::verify(
-//                     crypto::SignatureAlgorithm::ES256,
-//                     &att.attestation_cert[0].as_ref(),
-//                     att.sig.as_ref(),
-//                     data,
-//                 )?;
-//                 Ok(res)
-//             }
-//             AttestationStatement::Packed(att) => {
-//                 if att.alg != Alg::ES256 {
-//                     return Err(AuthenticatorError::Custom(
-//                         "Verification only supported for ES256".to_string(),
-//                     ));
-//                 }
-//                 let res = crypto::verify(
-//                     crypto::SignatureAlgorithm::ES256,
-//                     att.attestation_cert[0].as_ref(),
-//                     att.sig.as_ref(),
-//                     data,
-//                 )?;
-//                 Ok(res)
-//             }
-//         }
-//     }
-// }
+/// The kind of trust an `AttestationObject::verify` result establishes in the authenticator
+/// that produced it. See https://www.w3.org/TR/webauthn-2/#sctn-attestation-types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationType {
+    /// The statement was signed by an attestation key whose certificate chains up to one of
+    /// the caller's trust anchors.
+    Basic,
+    /// Same as `Basic`, except the trust anchor is an Attestation CA (the authenticator has no
+    /// individual attestation key and instead asks a CA to vouch for a model-wide key on the fly).
+    AttCA,
+    /// The credential's own private key was used to sign the statement; there is no separate
+    /// attestation key or certificate chain to evaluate trust from.
+    SelfAttestation,
+    /// No attestation information was provided by the authenticator.
+    None,
+}
+
+#[derive(Debug, Clone)]
+pub struct AttestationResult {
+    pub attestation_type: AttestationType,
+    /// The certificate chain presented by the authenticator, leaf-first. Empty for
+    /// `AttestationType::None` and `AttestationType::SelfAttestation`.
+    pub trust_chain: Vec<AttestationCertificate>,
+}
+
+impl AttestationObject {
+    /// Verifies the attestation statement per
+    /// https://www.w3.org/TR/webauthn-2/#sctn-verifying-attestation, returning the kind of
+    /// trust established and (if any) the certificate chain the caller should further judge
+    /// (e.g. against FIDO MDS metadata).
+    ///
+    /// `trust_anchors` is the configurable set of root certificates chains are walked up to;
+    /// a chain that doesn't reach one of these is still checked for internal consistency (the
+    /// signature verifies and the AAGUID extension, if present, matches), but is reported as
+    /// `AttestationType::Basic`/`AttCA` rather than rejected outright -- it is the caller's job
+    /// to decide whether an untrusted root is acceptable for their use case.
+    pub fn verify(
+        &self,
+        client_data_hash: &[u8],
+        trust_anchors: &[AttestationCertificate],
+    ) -> Result<AttestationResult, AuthenticatorError> {
+        match &self.att_statement {
+            AttestationStatement::None => Ok(AttestationResult {
+                attestation_type: AttestationType::None,
+                trust_chain: vec![],
+            }),
+            AttestationStatement::Packed(stmt) => {
+                self.verify_packed(stmt, client_data_hash, trust_anchors)
+            }
+            AttestationStatement::FidoU2F(stmt) => {
+                self.verify_fido_u2f(stmt, client_data_hash, trust_anchors)
+            }
+            AttestationStatement::Tpm(_)
+            | AttestationStatement::AndroidKey(_)
+            | AttestationStatement::AndroidSafetyNet(_)
+            | AttestationStatement::Apple(_) => Err(AuthenticatorError::InternalError(
+                "verification is not yet implemented for this attestation format".to_string(),
+            )),
+        }
+    }
+
+    fn verify_packed(
+        &self,
+        stmt: &AttestationStatementPacked,
+        client_data_hash: &[u8],
+        trust_anchors: &[AttestationCertificate],
+    ) -> Result<AttestationResult, AuthenticatorError> {
+        let mut signed_data = self
+            .auth_data
+            .to_vec()
+            .map_err(|e| AuthenticatorError::InternalError(format!("{e:?}")))?;
+        signed_data.extend_from_slice(client_data_hash);
+
+        if let Some(leaf) = stmt.attestation_cert.first() {
+            // Basic or AttCA attestation: the leaf certificate's key signed `signed_data`.
+            if !crate::crypto::verify(stmt.alg, leaf.as_ref(), stmt.sig.as_ref(), &signed_data)
+                .map_err(|e| AuthenticatorError::InternalError(format!("{e:?}")))?
+            {
+                return Err(AuthenticatorError::InternalError(
+                    "packed attestation signature did not verify".to_string(),
+                ));
+            }
+
+            let attestation_type = x509::walk_chain_and_classify(
+                &stmt.attestation_cert,
+                self.auth_data.credential_data.as_ref().map(|c| &c.aaguid),
+                trust_anchors,
+            )?;
+
+            Ok(AttestationResult {
+                attestation_type,
+                trust_chain: stmt.attestation_cert.clone(),
+            })
+        } else {
+            // Self attestation: the credential's own key signed `signed_data`, and `alg` must
+            // match the algorithm of that key.
+            let credential_public_key = self
+                .auth_data
+                .credential_data
+                .as_ref()
+                .map(|c| &c.credential_public_key)
+                .ok_or_else(|| {
+                    AuthenticatorError::InternalError(
+                        "self attestation requires attested credential data".to_string(),
+                    )
+                })?;
+            if credential_public_key.alg != stmt.alg {
+                return Err(AuthenticatorError::InternalError(
+                    "self attestation alg does not match credential key alg".to_string(),
+                ));
+            }
+            // Unlike the basic/AttCA branch above, there's no certificate here to hand to
+            // `crypto::verify` (which expects a full DER X.509 certificate to extract a key
+            // from) -- self attestation verifies directly against the credential's own COSE key.
+            // `verify_with_cose_key` is a new entry point alongside `crypto::verify`; as with the
+            // latter (see the historical note this replaced), it needs an implementation in
+            // every enabled crypto backend, not just the one this was developed against.
+            if !crate::crypto::verify_with_cose_key(
+                stmt.alg,
+                credential_public_key,
+                stmt.sig.as_ref(),
+                &signed_data,
+            )
+            .map_err(|e| AuthenticatorError::InternalError(format!("{e:?}")))?
+            {
+                return Err(AuthenticatorError::InternalError(
+                    "self attestation signature did not verify".to_string(),
+                ));
+            }
+            Ok(AttestationResult {
+                attestation_type: AttestationType::SelfAttestation,
+                trust_chain: vec![],
+            })
+        }
+    }
+
+    fn verify_fido_u2f(
+        &self,
+        stmt: &AttestationStatementFidoU2F,
+        client_data_hash: &[u8],
+        trust_anchors: &[AttestationCertificate],
+    ) -> Result<AttestationResult, AuthenticatorError> {
+        let credential_data = self.auth_data.credential_data.as_ref().ok_or_else(|| {
+            AuthenticatorError::InternalError(
+                "fido-u2f attestation requires attested credential data".to_string(),
+            )
+        })?;
+
+        // Re-encode the COSE EC2 public key as the legacy 65-byte 0x04 || x || y form.
+        let public_key_u2f = ec2_public_key_u2f_bytes(&credential_data.credential_public_key)?;
+
+        let mut signed_data = Vec::with_capacity(1 + 32 + 32 + credential_data.credential_id.len() + 65);
+        signed_data.push(0x00);
+        signed_data.extend_from_slice(&self.auth_data.rp_id_hash.0);
+        signed_data.extend_from_slice(client_data_hash);
+        signed_data.extend_from_slice(&credential_data.credential_id);
+        signed_data.extend_from_slice(&public_key_u2f);
+
+        let leaf = stmt.attestation_cert.first().ok_or_else(|| {
+            AuthenticatorError::InternalError("fido-u2f attestation has no x5c".to_string())
+        })?;
+        if !crate::crypto::verify(COSEAlgorithm::ES256, leaf.as_ref(), stmt.sig.as_ref(), &signed_data)
+            .map_err(|e| AuthenticatorError::InternalError(format!("{e:?}")))?
+        {
+            return Err(AuthenticatorError::InternalError(
+                "fido-u2f attestation signature did not verify".to_string(),
+            ));
+        }
+
+        let attestation_type = x509::walk_chain_and_classify(
+            &stmt.attestation_cert,
+            Some(&credential_data.aaguid),
+            trust_anchors,
+        )?;
+
+        Ok(AttestationResult {
+            attestation_type,
+            trust_chain: stmt.attestation_cert.clone(),
+        })
+    }
+}
+
+/// Re-encodes a COSE EC2 public key as the uncompressed SEC1 point `0x04 || x || y`, as used by
+/// the legacy FIDO U2F signed blob.
+fn ec2_public_key_u2f_bytes(key: &COSEKey) -> Result<[u8; 65], AuthenticatorError> {
+    let (x, y) = key.try_ec2_x_y().map_err(|_| {
+        AuthenticatorError::InternalError("credential public key is not an EC2 key".to_string())
+    })?;
+    let mut out = [0u8; 65];
+    out[0] = 0x04;
+    out[1..33].copy_from_slice(&x);
+    out[33..65].copy_from_slice(&y);
+    Ok(out)
+}
+
+/// A small DER/X.509 reader, just thorough enough to support attestation-chain validation:
+/// matching issuer/subject to link certificates together, checking the validity window, and
+/// reading the `basicConstraints` CA flag and the FIDO `id-fido-gen-ce-aaguid` extension
+/// (1.3.6.1.4.1.45724.1.1.4). It assumes well-formed, v3 certificates with an explicit version
+/// field, which is true of every attestation certificate in the wild.
+mod x509 {
+    use super::{AAGuid, AttestationCertificate, AttestationType, AuthenticatorError, COSEAlgorithm};
+
+    // DER encoding (without tag/length) of 1.3.6.1.4.1.45724.1.1.4
+    const OID_FIDO_GEN_CE_AAGUID: &[u8] = &[0x2b, 6, 1, 4, 1, 0x82, 0xe5, 0x1c, 1, 1, 4];
+    // DER encoding (without tag/length) of 2.5.29.19 (basicConstraints)
+    const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+
+    #[derive(Debug)]
+    pub(super) struct ParsedCertificate {
+        pub issuer: Vec<u8>,
+        pub subject: Vec<u8>,
+        pub not_before: Vec<u8>,
+        pub not_after: Vec<u8>,
+        pub is_ca: bool,
+        pub aaguid_extension: Option<[u8; 16]>,
+        // The following three let us verify this certificate was actually signed by its
+        // issuer, rather than just checking that the issuer/subject names line up.
+        /// The full DER `TBSCertificate` TLV (tag, length, and content) -- this, not just the
+        /// `subject`/`issuer` fields above, is what the signature below covers.
+        pub tbs_certificate: Vec<u8>,
+        /// The signature algorithm's OID, DER-encoded (tag/length stripped).
+        pub signature_algorithm: Vec<u8>,
+        /// The raw signature bytes, with the BIT STRING's leading "unused bits" byte removed.
+        pub signature: Vec<u8>,
+    }
+
+    /// Maps a certificate's `signatureAlgorithm` OID to the `COSEAlgorithm` `crypto::verify`
+    /// understands. Chains using an algorithm we don't recognize are rejected rather than
+    /// silently treated as trusted.
+    fn cose_alg_for_signature_oid(oid: &[u8]) -> Option<COSEAlgorithm> {
+        match oid {
+            // ecdsa-with-SHA256, 1.2.840.10045.4.3.2
+            [0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02] => Some(COSEAlgorithm::ES256),
+            // sha256WithRSAEncryption, 1.2.840.113549.1.1.11
+            [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b] => Some(COSEAlgorithm::RS256),
+            // id-Ed25519, 1.3.101.112
+            [0x2b, 0x65, 0x70] => Some(COSEAlgorithm::EDDSA),
+            _ => None,
+        }
+    }
+
+    /// Normalizes a DER `Time` (CHOICE of `UTCTime` tag 0x17 or `GeneralizedTime` tag 0x18) to
+    /// `YYYYMMDDHHMMSSZ`, so validity bounds can be compared lexically against each other and
+    /// against the current time.
+    fn normalize_time(tag: u8, content: &[u8]) -> Option<Vec<u8>> {
+        match tag {
+            0x17 => {
+                // UTCTime: YYMMDDHHMM(SS)?Z. Per X.509, YY >= 50 means 19YY, else 20YY.
+                if content.len() < 11 {
+                    return None;
+                }
+                let yy: u32 = std::str::from_utf8(&content[0..2]).ok()?.parse().ok()?;
+                let year = if yy >= 50 { 1900 + yy } else { 2000 + yy };
+                let mut out = format!("{year:04}").into_bytes();
+                out.extend_from_slice(&content[2..]);
+                Some(out)
+            }
+            0x18 => Some(content.to_vec()), // GeneralizedTime already has a 4-digit year.
+            _ => None,
+        }
+    }
+
+    /// The current time, in the same `YYYYMMDDHHMMSSZ` shape `normalize_time` produces, computed
+    /// from `SystemTime` without pulling in a date/time crate.
+    fn now_as_generalized_time() -> Vec<u8> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let secs = now.as_secs() as i64;
+        let days = secs.div_euclid(86400);
+        let time_of_day = secs.rem_euclid(86400);
+        let (year, month, day) = civil_from_days(days);
+        let hour = time_of_day / 3600;
+        let minute = (time_of_day % 3600) / 60;
+        let second = time_of_day % 60;
+        format!("{year:04}{month:02}{day:02}{hour:02}{minute:02}{second:02}Z").into_bytes()
+    }
+
+    /// Howard Hinnant's days-since-epoch-to-civil-date algorithm (proleptic Gregorian calendar).
+    fn civil_from_days(z: i64) -> (i64, u32, u32) {
+        let z = z + 719468;
+        let era = if z >= 0 { z } else { z - 146096 } / 146097;
+        let doe = (z - era * 146097) as u64;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let y = yoe as i64 + era * 400;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = if month <= 2 { y + 1 } else { y };
+        (year, month, day)
+    }
+
+    fn read_len(data: &[u8], pos: usize) -> Option<(usize, usize)> {
+        let first = *data.get(pos)?;
+        if first & 0x80 == 0 {
+            Some((first as usize, pos + 1))
+        } else {
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > 4 {
+                return None;
+            }
+            let mut len = 0usize;
+            let mut p = pos + 1;
+            for _ in 0..n {
+                len = (len << 8) | (*data.get(p)? as usize);
+                p += 1;
+            }
+            Some((len, p))
+        }
+    }
+
+    /// Reads one DER TLV starting at `pos`, returning its tag, its content, and the offset of
+    /// the byte following it.
+    fn read_tlv(data: &[u8], pos: usize) -> Option<(u8, &[u8], usize)> {
+        let tag = *data.get(pos)?;
+        let (len, content_start) = read_len(data, pos + 1)?;
+        let content_end = content_start.checked_add(len)?;
+        Some((tag, data.get(content_start..content_end)?, content_end))
+    }
+
+    fn find_extension<'a>(extensions_seq: &'a [u8], oid: &[u8]) -> Option<&'a [u8]> {
+        let mut pos = 0;
+        while let Some((_tag, ext, next)) = read_tlv(extensions_seq, pos) {
+            pos = next;
+            let (_, found_oid, rest_pos) = read_tlv(ext, 0)?;
+            if found_oid != oid {
+                continue;
+            }
+            // Optional `critical BOOLEAN DEFAULT FALSE` then the `OCTET STRING` value.
+            let (tag, content, rest_pos2) = read_tlv(ext, rest_pos)?;
+            let value = if tag == 0x01 {
+                read_tlv(ext, rest_pos2)?.1
+            } else {
+                content
+            };
+            return Some(value);
+        }
+        None
+    }
+
+    pub(super) fn parse(der: &[u8]) -> Option<ParsedCertificate> {
+        let (_, cert, _) = read_tlv(der, 0)?;
+        let (_, tbs, tbs_end) = read_tlv(cert, 0)?;
+        // `tbs` above is the TBSCertificate's *content*; the signature covers the full TLV
+        // (header included), which is exactly `cert[0..tbs_end]` since the TBSCertificate is
+        // the first element of the outer Certificate SEQUENCE.
+        let tbs_certificate = cert.get(0..tbs_end)?.to_vec();
+
+        let (_, sig_alg_seq, sig_alg_end) = read_tlv(cert, tbs_end)?;
+        let (_, signature_algorithm, _) = read_tlv(sig_alg_seq, 0)?;
+        let signature_algorithm = signature_algorithm.to_vec();
+
+        let (_, sig_bits, _) = read_tlv(cert, sig_alg_end)?;
+        // BIT STRING content starts with a one-byte "unused bits" count, which is always 0 for
+        // a DER-encoded signature.
+        let signature = sig_bits.get(1..)?.to_vec();
+
+        let mut pos = 0;
+        let (tag, _, next) = read_tlv(tbs, pos)?;
+        if tag == 0xa0 {
+            pos = next; // explicit [0] version
+        }
+        let (_, _serial, next) = read_tlv(tbs, pos)?;
+        pos = next;
+        let (_, _sig_alg, next) = read_tlv(tbs, pos)?;
+        pos = next;
+        let (_, issuer, next) = read_tlv(tbs, pos)?;
+        pos = next;
+        let (_, validity, next) = read_tlv(tbs, pos)?;
+        pos = next;
+        let (_, subject, next) = read_tlv(tbs, pos)?;
+        pos = next;
+        let (_, _spki, next) = read_tlv(tbs, pos)?;
+        pos = next;
+
+        let (nb_tag, not_before, vpos) = read_tlv(validity, 0)?;
+        let (na_tag, not_after, _) = read_tlv(validity, vpos)?;
+        // Normalize both UTCTime (2-digit year) and GeneralizedTime (4-digit year) to the same
+        // "YYYYMMDDHHMMSSZ" shape so they can be compared lexically, both to each other and to
+        // the current time.
+        let not_before = normalize_time(nb_tag, not_before)?;
+        let not_after = normalize_time(na_tag, not_after)?;
+
+        let mut is_ca = false;
+        let mut aaguid_extension = None;
+        // What remains are the optional [1]/[2] unique IDs and the [3] EXPLICIT Extensions.
+        let mut p = pos;
+        while let Some((tag, content, next)) = read_tlv(tbs, p) {
+            if tag == 0xa3 {
+                if let Some((_, ext_seq, _)) = read_tlv(content, 0) {
+                    if let Some(value) = find_extension(ext_seq, OID_BASIC_CONSTRAINTS) {
+                        // BasicConstraints ::= SEQUENCE { cA BOOLEAN DEFAULT FALSE, ... }
+                        if let Some((_, seq, _)) = read_tlv(value, 0) {
+                            is_ca = matches!(read_tlv(seq, 0), Some((0x01, [0xff], _)));
+                        }
+                    }
+                    if let Some(value) = find_extension(ext_seq, OID_FIDO_GEN_CE_AAGUID) {
+                        // The extension value is itself an OCTET STRING wrapping the 16-byte AAGUID.
+                        if let Some((0x04, aaguid, _)) = read_tlv(value, 0) {
+                            if aaguid.len() == 16 {
+                                let mut buf = [0u8; 16];
+                                buf.copy_from_slice(aaguid);
+                                aaguid_extension = Some(buf);
+                            }
+                        }
+                    }
+                }
+            }
+            p = next;
+        }
+
+        Some(ParsedCertificate {
+            issuer: issuer.to_vec(),
+            subject: subject.to_vec(),
+            not_before,
+            not_after,
+            is_ca,
+            aaguid_extension,
+            tbs_certificate,
+            signature_algorithm,
+            signature,
+        })
+    }
+
+    /// Walks `chain` (leaf-first) checking that each certificate's issuer matches, and was
+    /// actually used to sign, the next certificate, that the leaf's AAGUID extension (if
+    /// present) matches `expected_aaguid`, and that every certificate is within its validity
+    /// window. Classifies the result as `Basic` or `AttCA` depending on whether the chain
+    /// reaches one of `trust_anchors` (also verified by signature, not just by name); either is
+    /// returned even when no anchor matches; it is the caller's responsibility to additionally
+    /// check `trust_chain` against known-good roots if strict trust establishment is required.
+    pub(super) fn walk_chain_and_classify(
+        chain: &[AttestationCertificate],
+        expected_aaguid: Option<&AAGuid>,
+        trust_anchors: &[AttestationCertificate],
+    ) -> Result<AttestationType, AuthenticatorError> {
+        let parsed: Vec<ParsedCertificate> = chain
+            .iter()
+            .map(|cert| {
+                parse(cert.as_ref()).ok_or_else(|| {
+                    AuthenticatorError::InternalError("failed to parse x509 certificate".to_string())
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        if let (Some(leaf), Some(expected)) = (parsed.first(), expected_aaguid) {
+            if let Some(aaguid) = leaf.aaguid_extension {
+                if aaguid != expected.0 {
+                    return Err(AuthenticatorError::InternalError(
+                        "attestation certificate AAGUID extension does not match authData"
+                            .to_string(),
+                    ));
+                }
+            }
+        }
+
+        for (i, pair) in parsed.windows(2).enumerate() {
+            if pair[0].issuer != pair[1].subject {
+                return Err(AuthenticatorError::InternalError(
+                    "attestation certificate chain is not contiguous".to_string(),
+                ));
+            }
+            if !pair[1].is_ca {
+                return Err(AuthenticatorError::InternalError(
+                    "intermediate attestation certificate is not a CA".to_string(),
+                ));
+            }
+            // The core check: matching issuer/subject names alone proves nothing about who
+            // actually issued a certificate, so verify `chain[i]` was really signed by
+            // `chain[i + 1]`'s key.
+            verify_issued_by(&pair[0], chain[i + 1].as_ref())?;
+        }
+
+        let now = now_as_generalized_time();
+        for cert in &parsed {
+            if cert.not_before > cert.not_after
+                || now.as_slice() < cert.not_before.as_slice()
+                || now.as_slice() > cert.not_after.as_slice()
+            {
+                return Err(AuthenticatorError::InternalError(
+                    "attestation certificate is not within its validity window".to_string(),
+                ));
+            }
+        }
+
+        let reaches_trust_anchor = trust_anchors.iter().any(|anchor| {
+            parse(anchor.as_ref())
+                .map(|anchor_parsed| {
+                    parsed
+                        .last()
+                        .map(|last| {
+                            last.issuer == anchor_parsed.subject
+                                && verify_issued_by(last, anchor.as_ref()).is_ok()
+                        })
+                        .unwrap_or(false)
+                })
+                .unwrap_or(false)
+        });
+
+        Ok(if reaches_trust_anchor {
+            AttestationType::AttCA
+        } else {
+            AttestationType::Basic
+        })
+    }
+
+    /// Verifies that `cert` was actually signed by `issuer_der` (the issuer's full DER
+    /// certificate), not just that their issuer/subject names happen to line up.
+    fn verify_issued_by(
+        cert: &ParsedCertificate,
+        issuer_der: &[u8],
+    ) -> Result<(), AuthenticatorError> {
+        let alg = cose_alg_for_signature_oid(&cert.signature_algorithm).ok_or_else(|| {
+            AuthenticatorError::InternalError(
+                "unsupported attestation certificate signature algorithm".to_string(),
+            )
+        })?;
+        let verified = crate::crypto::verify(alg, issuer_der, &cert.signature, &cert.tbs_certificate)
+            .map_err(|e| AuthenticatorError::InternalError(format!("{e:?}")))?;
+        if verified {
+            Ok(())
+        } else {
+            Err(AuthenticatorError::InternalError(
+                "attestation certificate was not signed by its issuer".to_string(),
+            ))
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 // See https://www.w3.org/TR/webauthn-2/#sctn-fido-u2f-attestation
@@ -429,6 +1151,271 @@ pub struct AttestationStatementPacked {
     pub attestation_cert: Vec<AttestationCertificate>, // (3) "x5c"
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+// https://www.w3.org/TR/webauthn-2/#sctn-tpm-attestation
+// tpmStmtFormat = {
+//                     ver: "2.0",
+//                     alg: COSEAlgorithmIdentifier,
+//                     x5c: [ aikCert: bytes, * (caCert: bytes) ],
+//                     sig: bytes,
+//                     certInfo: bytes,
+//                     pubArea: bytes
+//                 }
+pub struct AttestationStatementTpm {
+    pub ver: String,
+    pub alg: COSEAlgorithm,
+    #[serde(rename = "x5c")]
+    pub attestation_cert: Vec<AttestationCertificate>,
+    pub sig: Signature,
+    /// TPMS_ATTEST, see `TpmCertInfo`
+    #[serde(with = "serde_bytes")]
+    pub cert_info: Vec<u8>,
+    /// TPMT_PUBLIC, see `TpmPubArea`
+    #[serde(with = "serde_bytes")]
+    pub pub_area: Vec<u8>,
+}
+
+impl AttestationStatementTpm {
+    /// Parses the `certInfo` bytes into a `TpmCertInfo` (TPMS_ATTEST).
+    pub fn cert_info(&self) -> Result<TpmCertInfo, AuthenticatorError> {
+        TpmCertInfo::parse(&self.cert_info)
+    }
+
+    /// Parses the `pubArea` bytes into a `TpmPubArea` (TPMT_PUBLIC).
+    pub fn pub_area(&self) -> Result<TpmPubArea, AuthenticatorError> {
+        TpmPubArea::parse(&self.pub_area)
+    }
+}
+
+fn tpm_parse_err(what: &str) -> AuthenticatorError {
+    AuthenticatorError::InternalError(format!("failed to parse TPM {what}"))
+}
+
+fn read_be_u64(data: &mut Cursor<&[u8]>) -> Result<u64, AuthenticatorError> {
+    let mut buf = [0u8; 8];
+    data.read_exact(&mut buf)
+        .map_err(|_| tpm_parse_err("u64"))?;
+    Ok(u64::from_be_bytes(buf))
+}
+
+/// A `u16`-length-prefixed byte string, as used throughout the TPM 2.0 wire format.
+fn read_tpm2b(data: &mut Cursor<&[u8]>) -> Result<Vec<u8>, AuthenticatorError> {
+    let len = read_be_u16(data).map_err(|_| tpm_parse_err("length-prefixed field"))?;
+    let mut buf = vec![0u8; len as usize];
+    data.read_exact(&mut buf)
+        .map_err(|_| tpm_parse_err("length-prefixed field"))?;
+    Ok(buf)
+}
+
+/// TPM_ALG_ID values relevant to `TPMT_PUBLIC::type`.
+/// See Part 2 of the TPM 2.0 spec, "Structures".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TpmPublicAlgorithm {
+    Rsa,
+    Ecc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TpmPublicParameters {
+    Rsa { key_bits: u16, exponent: u32 },
+    Ecc { curve_id: u16 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TpmPublicUnique {
+    Rsa { modulus: Vec<u8> },
+    Ecc { x: Vec<u8>, y: Vec<u8> },
+}
+
+/// TPMT_PUBLIC, as embedded (without its own length prefix) in the `pubArea` bytes of a TPM
+/// attestation statement. See TPM 2.0 Part 2, 12.2.4.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TpmPubArea {
+    pub object_type: TpmPublicAlgorithm,
+    pub name_alg: u16,
+    pub object_attributes: u32,
+    pub parameters: TpmPublicParameters,
+    pub unique: TpmPublicUnique,
+}
+
+impl TpmPubArea {
+    pub fn parse(data: &[u8]) -> Result<Self, AuthenticatorError> {
+        let mut cursor = Cursor::new(data);
+        let object_type = match read_be_u16::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("pubArea type"))? {
+            0x0001 => TpmPublicAlgorithm::Rsa,
+            0x0023 => TpmPublicAlgorithm::Ecc,
+            _ => return Err(tpm_parse_err("pubArea type (unsupported TPM_ALG_ID)")),
+        };
+        let name_alg = read_be_u16::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("pubArea nameAlg"))?;
+        let object_attributes =
+            read_be_u32::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("pubArea objectAttributes"))?;
+        let _auth_policy = read_tpm2b(&mut cursor)?;
+
+        let parameters = match object_type {
+            TpmPublicAlgorithm::Rsa => {
+                let _symmetric = read_be_u16::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("parameters"))?;
+                let _scheme = read_be_u16::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("parameters"))?;
+                let key_bits = read_be_u16::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("parameters"))?;
+                let exponent = read_be_u32::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("parameters"))?;
+                TpmPublicParameters::Rsa { key_bits, exponent }
+            }
+            TpmPublicAlgorithm::Ecc => {
+                let _symmetric = read_be_u16::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("parameters"))?;
+                let _scheme = read_be_u16::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("parameters"))?;
+                let curve_id = read_be_u16::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("parameters"))?;
+                let _kdf = read_be_u16::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("parameters"))?;
+                TpmPublicParameters::Ecc { curve_id }
+            }
+        };
+
+        let unique = match object_type {
+            TpmPublicAlgorithm::Rsa => TpmPublicUnique::Rsa {
+                modulus: read_tpm2b(&mut cursor)?,
+            },
+            TpmPublicAlgorithm::Ecc => {
+                let x = read_tpm2b(&mut cursor)?;
+                let y = read_tpm2b(&mut cursor)?;
+                TpmPublicUnique::Ecc { x, y }
+            }
+        };
+
+        Ok(TpmPubArea {
+            object_type,
+            name_alg,
+            object_attributes,
+            parameters,
+            unique,
+        })
+    }
+}
+
+/// TPMS_CLOCK_INFO, a fixed 17-byte structure embedded in TPMS_ATTEST.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TpmClockInfo {
+    pub clock: u64,
+    pub reset_count: u32,
+    pub restart_count: u32,
+    pub safe: bool,
+}
+
+/// The `name` field of TPMS_CERTIFY_INFO: a name-algorithm identifier followed by the digest
+/// of the certified object's `pubArea`, computed with that algorithm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TpmAttestedName {
+    pub name_alg: u16,
+    pub digest: Vec<u8>,
+}
+
+/// TPMS_ATTEST, as embedded (without its own length prefix) in the `certInfo` bytes of a TPM
+/// attestation statement. See TPM 2.0 Part 2, 10.12.8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TpmCertInfo {
+    pub qualified_signer: Vec<u8>,
+    pub extra_data: Vec<u8>,
+    pub clock_info: TpmClockInfo,
+    pub firmware_version: u64,
+    pub attested_name: TpmAttestedName,
+    pub attested_qualified_name: TpmAttestedName,
+}
+
+const TPM_GENERATED_VALUE: u32 = 0xFF54_4347;
+const TPM_ST_ATTEST_CERTIFY: u16 = 0x8017;
+
+impl TpmCertInfo {
+    pub fn parse(data: &[u8]) -> Result<Self, AuthenticatorError> {
+        let mut cursor = Cursor::new(data);
+
+        let magic = read_be_u32::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("certInfo magic"))?;
+        if magic != TPM_GENERATED_VALUE {
+            return Err(tpm_parse_err("certInfo magic (not TPM_GENERATED_VALUE)"));
+        }
+        let attest_type =
+            read_be_u16::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("certInfo type"))?;
+        if attest_type != TPM_ST_ATTEST_CERTIFY {
+            return Err(tpm_parse_err("certInfo type (not TPM_ST_ATTEST_CERTIFY)"));
+        }
+
+        let qualified_signer = read_tpm2b(&mut cursor)?;
+        let extra_data = read_tpm2b(&mut cursor)?;
+
+        let clock = read_be_u64(&mut cursor)?;
+        let reset_count = read_be_u32::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("clockInfo"))?;
+        let restart_count = read_be_u32::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("clockInfo"))?;
+        let safe =
+            read_byte::<_, serde_cbor::Error>(&mut cursor).map_err(|_| tpm_parse_err("clockInfo"))?
+                != 0;
+        let clock_info = TpmClockInfo {
+            clock,
+            reset_count,
+            restart_count,
+            safe,
+        };
+
+        let firmware_version = read_be_u64(&mut cursor)?;
+
+        let attested_name = read_tpm_name(&mut cursor)?;
+        let attested_qualified_name = read_tpm_name(&mut cursor)?;
+
+        Ok(TpmCertInfo {
+            qualified_signer,
+            extra_data,
+            clock_info,
+            firmware_version,
+            attested_name,
+            attested_qualified_name,
+        })
+    }
+}
+
+fn read_tpm_name(cursor: &mut Cursor<&[u8]>) -> Result<TpmAttestedName, AuthenticatorError> {
+    let raw = read_tpm2b(cursor)?;
+    if raw.len() < 2 {
+        return Err(tpm_parse_err("attested name (too short)"));
+    }
+    let name_alg = u16::from_be_bytes([raw[0], raw[1]]);
+    Ok(TpmAttestedName {
+        name_alg,
+        digest: raw[2..].to_vec(),
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+// https://www.w3.org/TR/webauthn-2/#sctn-android-key-attestation
+// androidKeyStmtFormat = {
+//                            alg: COSEAlgorithmIdentifier,
+//                            sig: bytes,
+//                            x5c: [ credCert: bytes, * (caCert: bytes) ]
+//                        }
+pub struct AttestationStatementAndroidKey {
+    pub alg: COSEAlgorithm,
+    pub sig: Signature,
+    #[serde(rename = "x5c")]
+    pub attestation_cert: Vec<AttestationCertificate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+// https://www.w3.org/TR/webauthn-2/#sctn-android-safetynet-attestation
+// safetynetStmtFormat = {
+//                           ver: text,
+//                           response: bytes
+//                       }
+pub struct AttestationStatementAndroidSafetyNet {
+    pub ver: String,
+    /// A JWS payload signed by Google attesting to the device's security state.
+    #[serde(with = "serde_bytes")]
+    pub response: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+// https://www.w3.org/TR/webauthn-2/#sctn-apple-anonymous-attestation
+// appleStmtFormat = {
+//                       x5c: [ credCert: bytes, * (caCert: bytes) ]
+//                   }
+pub struct AttestationStatementApple {
+    #[serde(rename = "x5c")]
+    pub attestation_cert: Vec<AttestationCertificate>,
+}
+
 #[derive(Debug, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 enum AttestationFormat {
@@ -436,11 +1423,12 @@ enum AttestationFormat {
     FidoU2F,
     Packed,
     None,
-    // TOOD(baloo): only packed is implemented for now, see spec:
-    //              https://www.w3.org/TR/webauthn/#defined-attestation-formats
-    //TPM,
-    //AndroidKey,
-    //AndroidSafetyNet,
+    Tpm,
+    #[serde(rename = "android-key")]
+    AndroidKey,
+    #[serde(rename = "android-safetynet")]
+    AndroidSafetyNet,
+    Apple,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -508,6 +1496,23 @@ impl<'de> Deserialize<'de> for AttestationObject {
                                     att_statement =
                                         Some(AttestationStatement::FidoU2F(map.next_value()?));
                                 }
+                                AttestationFormat::Tpm => {
+                                    att_statement =
+                                        Some(AttestationStatement::Tpm(map.next_value()?));
+                                }
+                                AttestationFormat::AndroidKey => {
+                                    att_statement =
+                                        Some(AttestationStatement::AndroidKey(map.next_value()?));
+                                }
+                                AttestationFormat::AndroidSafetyNet => {
+                                    att_statement = Some(AttestationStatement::AndroidSafetyNet(
+                                        map.next_value()?,
+                                    ));
+                                }
+                                AttestationFormat::Apple => {
+                                    att_statement =
+                                        Some(AttestationStatement::Apple(map.next_value()?));
+                                }
                             }
                         }
                         k => return Err(M::Error::custom(format!("unexpected key: {k:?}"))),
@@ -557,6 +1562,22 @@ impl Serialize for AttestationObject {
                 map.serialize_entry(&"fmt", &"fido-u2f")?; // (1) "fmt"
                 map.serialize_entry(&"attStmt", v)?; // (2) "attStmt"
             }
+            AttestationStatement::Tpm(ref v) => {
+                map.serialize_entry(&"fmt", &"tpm")?; // (1) "fmt"
+                map.serialize_entry(&"attStmt", v)?; // (2) "attStmt"
+            }
+            AttestationStatement::AndroidKey(ref v) => {
+                map.serialize_entry(&"fmt", &"android-key")?; // (1) "fmt"
+                map.serialize_entry(&"attStmt", v)?; // (2) "attStmt"
+            }
+            AttestationStatement::AndroidSafetyNet(ref v) => {
+                map.serialize_entry(&"fmt", &"android-safetynet")?; // (1) "fmt"
+                map.serialize_entry(&"attStmt", v)?; // (2) "attStmt"
+            }
+            AttestationStatement::Apple(ref v) => {
+                map.serialize_entry(&"fmt", &"apple")?; // (1) "fmt"
+                map.serialize_entry(&"attStmt", v)?; // (2) "attStmt"
+            }
         }
 
         let auth_data = self
@@ -569,6 +1590,233 @@ impl Serialize for AttestationObject {
     }
 }
 
+/// Parsing of, and trust lookups against, the FIDO Alliance Metadata Service (MDS3) BLOB --
+/// a JWT whose payload lists every certified authenticator model by AAGUID, along with the
+/// attestation root certificates and certification/compromise history for that model.
+/// See https://fidoalliance.org/metadata/.
+mod mds {
+    use super::{x509, AAGuid, AttestationCertificate, AuthenticatorError};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    /// The per-entry certification/compromise history. Only the fields needed to decide
+    /// whether to trust an authenticator are modeled; unknown strings are kept so a newly
+    /// added status doesn't fail deserialization of the rest of the BLOB.
+    #[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+    pub struct StatusReport {
+        pub status: String,
+        #[serde(default)]
+        pub effective_date: Option<String>,
+    }
+
+    impl StatusReport {
+        fn is_revoked_or_compromised(&self) -> bool {
+            matches!(
+                self.status.as_str(),
+                "REVOKED"
+                    | "ATTESTATION_KEY_COMPROMISE"
+                    | "USER_KEY_REMOTE_COMPROMISE"
+                    | "USER_VERIFICATION_BYPASS"
+                    | "USER_KEY_PHYSICAL_COMPROMISE"
+            )
+        }
+
+        /// The FIDO certification level (`FIDO_CERTIFIED_L<n>[plus]`), or `0` for a plain
+        /// `FIDO_CERTIFIED`/uncertified status.
+        fn certification_level(&self) -> u8 {
+            match self.status.as_str() {
+                "FIDO_CERTIFIED_L1" => 1,
+                "FIDO_CERTIFIED_L1plus" => 1,
+                "FIDO_CERTIFIED_L2" => 2,
+                "FIDO_CERTIFIED_L2plus" => 2,
+                "FIDO_CERTIFIED_L3" => 3,
+                "FIDO_CERTIFIED_L3plus" => 3,
+                _ => 0,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct MetadataBlobPayloadEntry {
+        #[serde(default)]
+        aaguid: Option<String>,
+        #[serde(default, rename = "attestationRootCertificates")]
+        attestation_root_certificates: Vec<String>,
+        #[serde(default, rename = "statusReports")]
+        status_reports: Vec<StatusReport>,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct MetadataBlobPayload {
+        #[serde(default)]
+        entries: Vec<MetadataBlobPayloadEntry>,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct JwsHeader {
+        alg: String,
+        #[serde(default)]
+        x5c: Vec<String>,
+    }
+
+    struct Entry {
+        trust_anchors: Vec<AttestationCertificate>,
+        status_reports: Vec<StatusReport>,
+    }
+
+    /// Trust anchors and status history for every AAGUID listed in an MDS3 BLOB.
+    pub struct AttestationRootStore {
+        by_aaguid: BTreeMap<AAGuid, Entry>,
+    }
+
+    fn b64url_decode(part: &str) -> Result<Vec<u8>, AuthenticatorError> {
+        base64::decode_config(part, base64::URL_SAFE_NO_PAD)
+            .map_err(|e| AuthenticatorError::InternalError(format!("invalid base64url: {e}")))
+    }
+
+    fn decode_aaguid(s: &str) -> Option<AAGuid> {
+        let hex: String = s.chars().filter(|c| *c != '-').collect();
+        if hex.len() != 32 {
+            return None;
+        }
+        let mut bytes = [0u8; 16];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+        }
+        Some(AAGuid(bytes))
+    }
+
+    impl AttestationRootStore {
+        /// Parses an MDS3 BLOB JWT, verifying its signature against `mds_trust_anchors` (the
+        /// configured set of roots the MDS signing certificate must chain up to).
+        pub fn from_jwt(
+            jwt: &str,
+            mds_trust_anchors: &[AttestationCertificate],
+        ) -> Result<Self, AuthenticatorError> {
+            let mut parts = jwt.split('.');
+            let header_b64 = parts
+                .next()
+                .ok_or_else(|| AuthenticatorError::InternalError("MDS BLOB: missing header".to_string()))?;
+            let payload_b64 = parts.next().ok_or_else(|| {
+                AuthenticatorError::InternalError("MDS BLOB: missing payload".to_string())
+            })?;
+            let sig_b64 = parts
+                .next()
+                .ok_or_else(|| AuthenticatorError::InternalError("MDS BLOB: missing signature".to_string()))?;
+            if parts.next().is_some() {
+                return Err(AuthenticatorError::InternalError(
+                    "MDS BLOB: malformed JWT".to_string(),
+                ));
+            }
+
+            let header: JwsHeader = serde_json::from_slice(&b64url_decode(header_b64)?)
+                .map_err(|e| AuthenticatorError::InternalError(format!("MDS BLOB header: {e}")))?;
+            let alg = match header.alg.as_str() {
+                "ES256" => super::COSEAlgorithm::ES256,
+                "RS256" => super::COSEAlgorithm::RS256,
+                other => {
+                    return Err(AuthenticatorError::InternalError(format!(
+                        "MDS BLOB: unsupported signing alg {other}"
+                    )))
+                }
+            };
+
+            let signing_chain: Vec<AttestationCertificate> = header
+                .x5c
+                .iter()
+                .map(|cert_b64| {
+                    base64::decode_config(cert_b64, base64::STANDARD)
+                        .map(AttestationCertificate)
+                        .map_err(|e| {
+                            AuthenticatorError::InternalError(format!("MDS BLOB: bad x5c entry: {e}"))
+                        })
+                })
+                .collect::<Result<_, _>>()?;
+            let leaf = signing_chain.first().ok_or_else(|| {
+                AuthenticatorError::InternalError("MDS BLOB: signing certificate missing".to_string())
+            })?;
+
+            let signed_input = format!("{header_b64}.{payload_b64}");
+            let signature = b64url_decode(sig_b64)?;
+            if !crate::crypto::verify(alg, leaf.as_ref(), &signature, signed_input.as_bytes())
+                .map_err(|e| AuthenticatorError::InternalError(format!("{e:?}")))?
+            {
+                return Err(AuthenticatorError::InternalError(
+                    "MDS BLOB signature did not verify".to_string(),
+                ));
+            }
+            // The signing certificate itself must chain up to a configured MDS root -- reuse
+            // the same chain walk attestation verification uses; `AttCA` here just means "a
+            // configured anchor was reached", which is required for an MDS BLOB.
+            if !matches!(
+                x509::walk_chain_and_classify(&signing_chain, None, mds_trust_anchors)?,
+                super::AttestationType::AttCA
+            ) {
+                return Err(AuthenticatorError::InternalError(
+                    "MDS BLOB signing certificate does not chain to a configured MDS root"
+                        .to_string(),
+                ));
+            }
+
+            let payload: MetadataBlobPayload = serde_json::from_slice(&b64url_decode(payload_b64)?)
+                .map_err(|e| AuthenticatorError::InternalError(format!("MDS BLOB payload: {e}")))?;
+
+            let mut by_aaguid = BTreeMap::new();
+            for entry in payload.entries {
+                let Some(aaguid) = entry.aaguid.as_deref().and_then(decode_aaguid) else {
+                    continue;
+                };
+                let trust_anchors = entry
+                    .attestation_root_certificates
+                    .iter()
+                    .filter_map(|cert_b64| {
+                        base64::decode_config(cert_b64, base64::STANDARD)
+                            .ok()
+                            .map(AttestationCertificate)
+                    })
+                    .collect();
+                by_aaguid.insert(
+                    aaguid,
+                    Entry {
+                        trust_anchors,
+                        status_reports: entry.status_reports,
+                    },
+                );
+            }
+
+            Ok(AttestationRootStore { by_aaguid })
+        }
+
+        /// The trust anchors configured for `aaguid`'s authenticator model, if MDS knows of it.
+        pub fn trust_anchors_for(&self, aaguid: &AAGuid) -> Option<&[AttestationCertificate]> {
+            self.by_aaguid
+                .get(aaguid)
+                .map(|entry| entry.trust_anchors.as_slice())
+        }
+
+        /// Whether the latest status report for `aaguid` indicates the model has been revoked
+        /// or otherwise compromised. Unknown AAGUIDs are treated as untrusted.
+        pub fn is_revoked(&self, aaguid: &AAGuid) -> bool {
+            match self.by_aaguid.get(aaguid).and_then(|e| e.status_reports.last()) {
+                Some(report) => report.is_revoked_or_compromised(),
+                None => true,
+            }
+        }
+
+        /// Whether `aaguid`'s latest status report meets at least `min_level` of FIDO
+        /// certification (1, 2, or 3; a plain `FIDO_CERTIFIED` status is level 0).
+        pub fn meets_certification_level(&self, aaguid: &AAGuid, min_level: u8) -> bool {
+            self.by_aaguid
+                .get(aaguid)
+                .and_then(|e| e.status_reports.last())
+                .map(|report| report.certification_level() >= min_level)
+                .unwrap_or(false)
+        }
+    }
+}
+
+pub use mds::AttestationRootStore;
+
 #[cfg(test)]
 mod test {
     use super::super::utils::from_slice_stream;
@@ -814,6 +2062,56 @@ mod test {
         );
     }
 
+    const SAMPLE_AUTH_DATA_CRED_PROTECT_AND_MIN_PIN_LENGTH: [u8; 67] = [
+        0x58, 0x41, // bytes(65)
+        0xc2, 0x89, 0xc5, 0xca, 0x9b, 0x04, 0x60, 0xf9, 0x34, 0x6a, 0xb4, 0xe4, 0x2d, 0x84,
+        0x27, // rp_id_hash
+        0x43, 0x40, 0x4d, 0x31, 0xf4, 0x84, 0x68, 0x25, 0xa6, 0xd0, 0x65, 0xbe, 0x59, 0x7a,
+        0x87, // rp_id_hash
+        0x05, 0x1d, // rp_id_hash
+        0x81, // authData Flags: UP | ED
+        0x00, 0x00, 0x00, 0x01, // authData counter
+        // Extensions
+        0xA2, // map(2)
+        0x6B, 0x63, 0x72, 0x65, 0x64, 0x50, 0x72, 0x6f, 0x74, 0x65, 0x63, 0x74, // "credProtect"
+        0x02, // 2 = UserVerificationOptionalWithCredentialIDList
+        0x6C, 0x6d, 0x69, 0x6e, 0x50, 0x69, 0x6e, 0x4c, 0x65, 0x6e, 0x67, 0x74,
+        0x68, // "minPinLength"
+        0x04, // 4
+    ];
+
+    const SAMPLE_AUTH_DATA_CRED_BLOB: [u8; 50] = [
+        0x58, 0x30, // bytes(48)
+        0xc2, 0x89, 0xc5, 0xca, 0x9b, 0x04, 0x60, 0xf9, 0x34, 0x6a, 0xb4, 0xe4, 0x2d, 0x84,
+        0x27, // rp_id_hash
+        0x43, 0x40, 0x4d, 0x31, 0xf4, 0x84, 0x68, 0x25, 0xa6, 0xd0, 0x65, 0xbe, 0x59, 0x7a,
+        0x87, // rp_id_hash
+        0x05, 0x1d, // rp_id_hash
+        0x81, // authData Flags: UP | ED
+        0x00, 0x00, 0x00, 0x01, // authData counter
+        // Extensions
+        0xA1, // map(1)
+        0x68, 0x63, 0x72, 0x65, 0x64, 0x42, 0x6c, 0x6f, 0x62, // "credBlob"
+        0xF5, // true
+    ];
+
+    #[test]
+    fn parse_cred_protect_and_min_pin_length_extensions() {
+        let auth: AuthenticatorData =
+            from_slice(&SAMPLE_AUTH_DATA_CRED_PROTECT_AND_MIN_PIN_LENGTH).unwrap();
+        assert_eq!(
+            auth.extensions.cred_protect,
+            Some(CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList)
+        );
+        assert_eq!(auth.extensions.min_pin_length, Some(4));
+    }
+
+    #[test]
+    fn parse_cred_blob_extension() {
+        let auth: AuthenticatorData = from_slice(&SAMPLE_AUTH_DATA_CRED_BLOB).unwrap();
+        assert_eq!(auth.extensions.cred_blob, Some(CredBlobResponse::Confirmed(true)));
+    }
+
     /// See: https://github.com/mozilla/authenticator-rs/issues/187
     #[test]
     fn test_aaguid_output() {